@@ -3,18 +3,64 @@
 
 use crate::DetectionSettings;
 use crate::GeneralSettings;
-use crate::MpvMsg;
-use crate::MpvState;
+use crate::Player;
+use crate::PlayerRegistry;
+use crate::PlaybackMode;
+use crate::PlaybackOptions;
+use crate::StudySession;
+use crate::QueuedScene;
+use crate::load_study_session;
+use crate::save_study_session;
+use crate::SessionAction;
+use crate::MPV_BACKEND;
+use crate::get_player_backends;
+use crate::next_speed;
+use crate::find_playing_scene;
+use crate::find_scene;
+use crate::scene_progress;
+use crate::PlayerAction;
+use crate::apply_player_action;
+use crate::VideoPreview;
+use crate::cover_img_path;
+use crate::cover_file_name;
 use crate::all_scenes;
 use crate::app::egui::Vec2;
 use crate::apply_case;
+use crate::autotune_ocr;
+use crate::autotune_samples;
 use crate::extract_timestamps;
-use crate::get_cached_creators;
-use crate::get_popular_creators;
+use crate::parse_timestamps;
+use crate::DiagnosticKind;
+use crate::ParsedTimestamps;
+use crate::find_ocr_preset_for_creator;
+use crate::get_provider;
+use crate::get_provider_name;
+use crate::MetadataProvider;
+use crate::load_http_settings;
+use crate::save_http_settings;
+use crate::HttpSettings;
+use crate::HttpClient;
+use crate::update_cache;
+use crate::get_cache_status;
+use crate::CacheStatus;
+use crate::clear_product_details_cache;
+use crate::now_in_secs;
+use crate::guess_from_files;
+use crate::identification_confidence;
+use crate::volume_order;
+use crate::get_provider_ids;
+use crate::load_ocr_presets;
 use crate::load_org;
-use crate::mpv_pause;
-use crate::mpv_stop;
-use crate::play_scene;
+use crate::ocr_preview_img;
+use crate::reconcile_scenes;
+use crate::apply_reconciliation;
+use crate::merge_scenes;
+use crate::keep_existing;
+use crate::chapters_from_text;
+use crate::chapters_from_files;
+use crate::apply_merge;
+use crate::MergeOptions;
+use crate::save_ocr_presets;
 use crate::save_org;
 use crate::save_md;
 use crate::save_playlist;
@@ -22,19 +68,21 @@ use crate::scene_detect;
 use crate::scene_ocr_img_path;
 use crate::scene_text_with_settings;
 use crate::scene_to_image;
-use crate::scrape_url;
-use crate::search_product;
 use crate::seconds_to_time;
 use crate::split_scene;
-use crate::update_cache;
-use crate::update_mpv_state;
+use crate::export_edl;
+use crate::upsert_ocr_preset;
 use crate::video_duration;
 use crate::File;
 use crate::Instructional;
+use crate::KeyColor;
+use crate::OcrPreset;
 use crate::OcrSettings;
+use crate::OcrStep;
+use crate::ReconciledScene;
+use crate::MergedScene;
 use crate::Scene;
 use crate::Video;
-use crate::watch_mpv;
 use eframe::{egui, epi};
 use egui::*;
 use itertools::EitherOrBoth::Both;
@@ -46,10 +94,17 @@ use std::collections::HashMap;
 use std::env;
 use std::path::Path;
 use std::path::PathBuf;
+use std::sync::Arc;
 use std::sync::mpsc::channel;
 use std::sync::mpsc::Receiver;
 use std::sync::mpsc::Sender;
 static BLANK: &str = "";
+/// The name of the app, also used as the window title when nothing is playing.
+static APP_NAME: &str = "eframe template";
+/// The number of search results to show in the title combo.
+static MAX_TITLE_CANDIDATES: usize = 50;
+/// Instructionals identified with lower confidence are not prefilled.
+static MIN_IDENTIFICATION_CONFIDENCE: f32 = 0.6;
 
 /// We derive Deserialize/Serialize so we can persist app state on shutdown.
 #[cfg_attr(feature = "persistence", derive(serde::Deserialize, serde::Serialize))]
@@ -67,8 +122,58 @@ pub struct App {
     scene_images: Vec<Vec<Option<egui::TextureId>>>,
     general_settings: GeneralSettings,
     ocr_settings: OcrSettings,
+    ocr_presets: Vec<OcrPreset>,
+    http_settings: HttpSettings,
+    // The metadata provider of the instructional, and the provider id and settings it was created with.
+    provider: Arc<dyn MetadataProvider>,
+    provider_key: (String, HttpSettings),
+    // The popular creators of the provider (for providers like the local one, listing them reads the disk).
+    popular_creators: Vec<String>,
+    // Errors to show to the user (e.g. failed requests).
+    errors: Vec<String>,
+    // The status shown in the cache status window (if open). It's computed when the window opens and after the cache is updated.
+    cache_status: Option<CacheStatus>,
+    // The confidence of the last identification from the video files.
+    identification_confidence: Option<f32>,
+    // The cover url and its texture (if the cover has been downloaded).
+    cover_image: Option<(String, Option<egui::TextureId>)>,
+    selected_ocr_preset: String,
+    ocr_preset_name: String,
+    ocr_preview_enabled: bool,
+    ocr_preview_scene: Option<(usize, usize)>,
+    ocr_previewed: Option<((usize, usize), OcrSettings)>,
+    ocr_preview_images: Vec<(String, egui::TextureId)>,
+    // Whether a preview is being created. Settings changed in the meantime are previewed once it's done.
+    ocr_preview_pending: bool,
+    title_proposals: Vec<TitleProposal>,
+    timestamps_preview: Option<ParsedTimestamps>,
+    // The scraped text and its parsed timestamps, so that the text is only parsed again when it changes.
+    parsed_timestamps: Option<(String, ParsedTimestamps)>,
+    // The text pasted in the import chapters window.
+    pasted_chapters: Option<String>,
+    // Whether applied timestamps are merged into the existing scenes (instead of replacing them).
+    merge_timestamps: bool,
+    merge_options: MergeOptions,
+    // The changes rejected in the merge preview, as (volume, row of the merge).
+    merge_kept_rows: Vec<(usize, usize)>,
+    reconciliation: Option<(usize, Vec<ReconciledScene>)>,
     detection_settings: DetectionSettings,
-    mpv_state: MpvState,
+    players: PlayerRegistry,
+    playback_options: PlaybackOptions,
+    study_session: StudySession,
+    show_study_session: bool,
+    // Whether the study session is being played (scenes are fed to the player one after the other).
+    study_session_playing: bool,
+    // The position of the study session when it was last saved.
+    study_session_saved_position: f64,
+    // Whether the player of the study session was running, to notice when it's closed.
+    study_session_player_running: bool,
+    // The playing scene that was last scrolled into view, so that the list only scrolls when another scene starts.
+    scrolled_to_playing: Option<(usize, usize)>,
+    // The title last set to the window, so that it's only set when the playing scene changes.
+    window_title: String,
+    // The scene previewed inside the app. It's found again by file and boundaries, as scenes may be removed or reordered while previewing.
+    preview: Option<(Scene, VideoPreview)>,
     busy: bool,
     total_tasks: f32,
     completed_tasks: f32,
@@ -77,8 +182,6 @@ pub struct App {
     recv: Receiver<Command>,
     job_sender: Sender<Job>,
     job_recv: Receiver<Job>,
-    mpv_sender: Sender<MpvMsg>,
-    mpv_recv: Receiver<MpvMsg>,
 }
 
 pub enum Command {
@@ -113,6 +216,39 @@ pub enum Command {
     AddPendingTasks {
         tasks: usize,
     },
+    ApplyOcrSettings {
+        settings: OcrSettings,
+        score: f32,
+    },
+    UpdateOcrPreview {
+        v_index: usize,
+        s_index: usize,
+        settings: OcrSettings,
+        images: Vec<(String, egui::TextureId)>,
+    },
+    ProposeTitle {
+        v_index: usize,
+        s_index: usize,
+        title: Option<String>,
+        image: Option<egui::TextureId>,
+    },
+    UpdateMetadata {
+        url: String,
+        timestamps: Option<String>,
+        details: Option<Instructional>,
+        errors: Vec<String>,
+    },
+    UpdateTitleCandidates {
+        creator: String,
+        results: Result<Vec<Instructional>, String>,
+    },
+    ProviderRefreshed {
+        result: Result<(), String>,
+        identify: bool,
+    },
+    ProductCacheUpdated {
+        result: Result<(), String>,
+    },
 }
 
 pub enum Job {
@@ -130,24 +266,59 @@ pub enum Job {
             s: &Scene,
         ) -> Option<egui::TextureId>,
     },
+    AutoTuneOcr {
+        max_samples: usize,
+    },
+    PreviewOcr {
+        v_index: usize,
+        s_index: usize,
+        settings: OcrSettings,
+    },
+    RecognizeTitle {
+        v_index: usize,
+        s_index: usize,
+    },
+    // Network jobs, run with the provider of the instructional.
+    FetchMetadata {
+        url: String,
+    },
+    SearchTitles {
+        creator: String,
+        query: String,
+    },
+    RefreshProvider {
+        // Whether the instructional is identified again once the refresh completes.
+        identify: bool,
+    },
+    UpdateProductCache {
+        full: bool,
+    },
+}
+
+/// A title recognized by OCR that is pending review.
+pub struct TitleProposal {
+    v_index: usize,
+    s_index: usize,
+    current: String,
+    proposed: String,
+    image: Option<egui::TextureId>,
+    selected: bool,
 }
 
 impl Default for App {
     fn default() -> Self {
         let (sender, recv) = channel();
         let (job_sender, job_recv) = channel();
-        let (mpv_sender, mpv_recv) = channel();
+        let http_settings = load_http_settings();
+        let instructional = Instructional::new();
+        let provider = get_provider(&instructional.provider, &http_settings);
+        let provider_key = (instructional.provider.to_string(), http_settings.clone());
+        let popular_creators = provider.popular_creators();
         Self {
             icons: HashMap::new(),
             file: BLANK.to_owned(), //refers to the index file (save file)
             last_selected_file: BLANK.to_owned(), //refers to the index file (save file)
-            instructional: Instructional {
-                creator: BLANK.to_owned(),
-                title: BLANK.to_owned(),
-                url: BLANK.to_owned(),
-                timestamps: BLANK.to_owned(),
-                videos: vec![],
-            },
+            instructional,
             candidate_creators: vec![
                 "John Danaher",
                 "Gordon Ryan",
@@ -169,8 +340,41 @@ impl Default for App {
             scene_images: vec![],
             general_settings: GeneralSettings::new(),
             ocr_settings: OcrSettings::new(),
+            ocr_presets: load_ocr_presets(),
+            http_settings,
+            provider,
+            provider_key,
+            popular_creators,
+            errors: vec![],
+            cache_status: None,
+            identification_confidence: None,
+            cover_image: None,
+            selected_ocr_preset: BLANK.to_owned(),
+            ocr_preset_name: BLANK.to_owned(),
+            ocr_preview_enabled: false,
+            ocr_preview_scene: None,
+            ocr_previewed: None,
+            ocr_preview_images: vec![],
+            ocr_preview_pending: false,
+            title_proposals: vec![],
+            timestamps_preview: None,
+            parsed_timestamps: None,
+            pasted_chapters: None,
+            merge_timestamps: false,
+            merge_options: MergeOptions::new(),
+            merge_kept_rows: vec![],
+            reconciliation: None,
             detection_settings: DetectionSettings::new(),
-            mpv_state: MpvState::new(),
+            players: PlayerRegistry::new(MPV_BACKEND, BLANK),
+            playback_options: PlaybackOptions::new(),
+            study_session: load_study_session(),
+            show_study_session: false,
+            study_session_playing: false,
+            study_session_saved_position: 0.0,
+            study_session_player_running: false,
+            scrolled_to_playing: None,
+            window_title: APP_NAME.to_owned(),
+            preview: None,
             busy: true,
             total_tasks: 0.0,
             completed_tasks: 0.0,
@@ -179,15 +383,13 @@ impl Default for App {
             recv,
             job_sender,
             job_recv,
-            mpv_sender,
-            mpv_recv,
         }
     }
 }
 
 impl epi::App for App {
     fn name(&self) -> &str {
-        "eframe template"
+        APP_NAME
     }
 
     /// Called once before the first frame.
@@ -204,7 +406,7 @@ impl epi::App for App {
             *self = epi::get_value(storage, epi::APP_KEY).unwrap_or_default()
         }
         self.icons.insert(
-            "add-circle-line.png",
+            "add-circle-line",
             load_texture_id(frame, get_icon("add-circle-line.png").as_path()).unwrap(),
         );
         self.icons.insert(
@@ -344,6 +546,11 @@ impl epi::App for App {
         epi::set_value(storage, epi::APP_KEY, self);
     }
 
+    /// Called once on shutdown. The players launched by the app are stopped with it.
+    fn on_exit(&mut self) {
+        self.players.stop_all();
+    }
+
     /// Called each time the UI needs repainting, which may be many times per second.
     /// Put your widgets into a `SidePanel`, `TopPanel`, `CentralPanel`, `Window` or `Area`.
     fn update(&mut self, ctx: &egui::CtxRef, frame: &epi::Frame) {
@@ -360,8 +567,41 @@ impl epi::App for App {
             scene_images,
             general_settings,
             ocr_settings,
+            ocr_presets,
+            http_settings,
+            provider,
+            provider_key,
+            popular_creators,
+            errors,
+            cache_status,
+            identification_confidence,
+            cover_image,
+            selected_ocr_preset,
+            ocr_preset_name,
+            ocr_preview_enabled,
+            ocr_preview_scene,
+            ocr_previewed,
+            ocr_preview_images,
+            ocr_preview_pending,
+            title_proposals,
+            timestamps_preview,
+            parsed_timestamps,
+            pasted_chapters,
+            merge_timestamps,
+            merge_options,
+            merge_kept_rows,
+            reconciliation,
             detection_settings,
-            mpv_state,
+            players,
+            playback_options,
+            study_session,
+            show_study_session,
+            study_session_playing,
+            study_session_saved_position,
+            study_session_player_running,
+            scrolled_to_playing,
+            window_title,
+            preview,
             busy,
             completed_tasks,
             total_tasks,
@@ -370,8 +610,6 @@ impl epi::App for App {
             recv,
             job_sender,
             job_recv,
-            mpv_sender,
-            mpv_recv,
         } = self;
 
         // Examples of how to create different panels and windows.
@@ -379,20 +617,44 @@ impl epi::App for App {
         // Tip: a good default choice is to just keep the `CentralPanel`.
         // For inspiration and more examples, go to https://emilk.github.io/egui
 
+        update_provider(provider, provider_key, popular_creators, &instructional.provider, http_settings);
+
+        // Follow the main player, even when it was launched outside the app.
+        players.main().attach();
+        let now_playing = {
+            let state = players.main().state();
+            match (state.running, &state.path) {
+                (true, Some(path)) => find_playing_scene(instructional, path, state.playback_time)
+                    .map(|(i, j)| (i, j, scene_progress(&instructional.videos[i].scenes[j], state.playback_time, state.duration))),
+                _ => None,
+            }
+        };
+        if players.main().state().running && !players.main().state().pause {
+            // Keep the highlighted scene and the progress up to date.
+            ctx.request_repaint();
+        }
+        if now_playing.is_none() {
+            *scrolled_to_playing = None;
+        }
+        let title = match now_playing {
+            Some((i, j, _)) => format!("{} - {}", instructional.videos[i].scenes[j].title, APP_NAME),
+            None => APP_NAME.to_owned(),
+        };
+        if *window_title != title {
+            frame.set_window_title(&title);
+            *window_title = title;
+        }
+
         egui::TopBottomPanel::top("top_panel").show(ctx, |ui| {
             egui::menu::bar(ui, |ui| {
                 ui.menu_button("File", |ui| {
                     if ui.button("New").clicked() {
                         *file = "".to_owned();
-                        *instructional = Instructional {
-                            creator: BLANK.to_owned(),
-                            title: BLANK.to_owned(),
-                            url: BLANK.to_owned(),
-                            timestamps: BLANK.to_owned(),
-                            videos: vec![],
-                        };
+                        *instructional = Instructional::new();
+                        *identification_confidence = None;
                         *use_creator_combo = false;
-                        *candidate_creators = get_cached_creators();
+                        update_provider(provider, provider_key, popular_creators, &instructional.provider, http_settings);
+                        *candidate_creators = provider.list_creators();
                         *use_title_combo = false;
                         *candidate_titles = vec![];
                         *candidate_urls = vec![];
@@ -418,6 +680,12 @@ impl epi::App for App {
                                 *last_selected_file = f.clone();
                                 *file = f.clone();
                                 *instructional = load_org(File::open(&f).unwrap());
+                                if let Some(preset) = find_ocr_preset_for_creator(ocr_presets, &instructional.creator) {
+                                    println!("Using OCR preset: {} for creator: {}", preset.name, instructional.creator);
+                                    *ocr_settings = preset.settings;
+                                    *selected_ocr_preset = preset.name.clone();
+                                    *ocr_preset_name = preset.name;
+                                }
                                 *scene_images = allocate_scene_images(frame, &instructional.videos);
                                 *total_tasks += instructional
                                     .videos
@@ -513,7 +781,8 @@ impl epi::App for App {
                                 let target_path = Path::new(&t);
                                 let target_file = File::create(target_path)
                                     .expect("Failed to open file for saving!");
-                                save_md(instructional, target_file);
+                                let with_cover = target_path.parent().map(|dir| dir.join(cover_file_name(&instructional.cover)).exists()).unwrap_or(false);
+                                save_md(instructional, target_file, with_cover);
                             }
                             None => {}
                         };
@@ -546,6 +815,23 @@ impl epi::App for App {
                         };
                     }
 
+                    if ui.button("Export as EDL").on_hover_text("Write mpv EDL files (one per scene, label and instructional) that refer to the original videos").clicked() {
+                        let dir = videos_dir(&instructional.videos).unwrap_or_else(|| {
+                            parent_dir(last_selected_file).unwrap_or("/".to_string())
+                        });
+                        let target = rfd::FileDialog::new()
+                            .set_directory(dir)
+                            .pick_folder();
+
+                        match target {
+                            Some(t) => {
+                                let written = export_edl(instructional, t.as_path());
+                                println!("Exported {} EDL file(s) to: {}", written.len(), t.display());
+                            }
+                            None => {}
+                        };
+                    }
+
                     if ui.button("Split").clicked() {
                         let old = instructional.clone();
                         let target = instructional.clone();
@@ -591,13 +877,50 @@ impl epi::App for App {
                     }
                 });
                 ui.menu_button("Tools", |ui| {
+                    if ui.button("OCR all scenes").clicked() {
+                        recognize_titles(sender, job_sender, instructional, None);
+                    }
                     if ui.button("Update cache").clicked() {
-                        update_cache("".to_string(), "".to_string());
+                        sender.send(Command::AddPendingTasks{tasks: 1}).expect("Failed to send AddPendingTasks command!");
+                        job_sender.send(Job::RefreshProvider{identify: false}).expect("Failed to send RefreshProvider command!");
+                    }
+                    if ui.button("Cache status").clicked() {
+                        *cache_status = Some(get_cache_status());
+                    }
+                    if ui.button("Study session").clicked() {
+                        *show_study_session = true;
                     }
                 });
                 ui.menu_button("Import", |ui| {
                     if ui.button("Video").clicked() {
                         add_video(&mut instructional.videos, last_selected_file);
+                        if instructional.creator.is_empty() && instructional.title.is_empty() {
+                            *identification_confidence = identify_instructional(instructional, candidate_urls, candidate_titles, &**provider, errors);
+                            *use_title_combo = !candidate_titles.is_empty();
+                        }
+                    }
+                    if ui.button("Chapters from text").clicked() {
+                        *pasted_chapters = Some(BLANK.to_owned());
+                    }
+                    if ui.button("Chapters from file").clicked() {
+                        let dir = videos_dir(&instructional.videos).unwrap_or_else(|| or(last_selected_file.to_string(), "/".to_string()));
+                        let target = rfd::FileDialog::new()
+                            .add_filter("Chapter files", &["txt", "json"])
+                            .set_directory(dir)
+                            .pick_files();
+                        if let Some(mut paths) = target {
+                            // One file per volume.
+                            paths.sort();
+                            match chapters_from_files(&paths) {
+                                Ok(timestamps) => {
+                                    instructional.timestamps = timestamps;
+                                    *timestamps_preview = Some(parse_timestamps(&instructional.timestamps));
+                                    *merge_timestamps = instructional.videos.iter().any(|v| !v.scenes.is_empty());
+                                    merge_kept_rows.clear();
+                                },
+                                Err(e) => errors.push(e),
+                            }
+                        }
                     }
                 });
                 ui.menu_button("Help", |ui| {
@@ -605,6 +928,10 @@ impl epi::App for App {
                         //TODO: implement about
                     }
                 });
+                if let Some((i, j, _)) = now_playing {
+                    ui.separator();
+                    ui.label(format!("Now playing: {}", instructional.videos[i].scenes[j].title));
+                }
             });
         });
 
@@ -639,25 +966,39 @@ impl epi::App for App {
 
         egui::CentralPanel::default().show(ctx, |ui| {
             // The central panel the region left after adding TopPanel's and SidePanel's
+            ui.horizontal(|ui| {
+                ui.label("Provider: ");
+                egui::ComboBox::from_id_source("metadata-provider")
+                    .selected_text(provider.name())
+                    .show_ui(ui, |ui| {
+                        get_provider_ids().into_iter().for_each(|id| {
+                            if ui.selectable_value(&mut instructional.provider, id.to_string(), get_provider_name(id)).changed() {
+                                update_provider(provider, provider_key, popular_creators, id, http_settings);
+                                *candidate_creators = popular_creators.to_vec();
+                                *candidate_titles = vec![];
+                                *candidate_urls = vec![];
+                            }
+                        });
+                    });
+            });
             ui.label("Creator: ");
             ui.horizontal(|ui| {
                 if *use_creator_combo {
-                    if instructional.creator.is_empty() {
+                    if instructional.creator.is_empty() && !candidate_creators.is_empty() {
                         instructional.creator = candidate_creators[0].to_string();
                     }
                     if ui.add(egui::ImageButton::new(*icons.get("keyboard-box-line").unwrap(), (10.0, 10.0))).on_hover_text("Enter creator manually").clicked() {
                         *use_creator_combo=false;
                     }
 
-                    let popular = get_popular_creators();
-                    if candidate_creators.len() == popular.len() {
+                    if candidate_creators.len() == popular_creators.len() {
 
                         if ui.add(egui::ImageButton::new(*icons.get("star-line").unwrap(), (10.0, 10.0))).on_hover_text("Show all").clicked() {
-                            *candidate_creators = get_cached_creators();
+                            *candidate_creators = provider.list_creators();
                         }
                     } else {
                         if ui.add(egui::ImageButton::new(*icons.get("star-half-line").unwrap(), (10.0, 10.0))).on_hover_text("Only show popular").clicked() {
-                            *candidate_creators = popular;
+                            *candidate_creators = popular_creators.to_vec();
                         }
                     }
                     egui::ComboBox::from_label("Select creator")
@@ -666,7 +1007,7 @@ impl epi::App for App {
                             candidate_creators.iter()
                                 .for_each(|t| {
                                     if ui.selectable_value(&mut instructional.creator, t.to_string(), t).changed() {
-                                        refresh_titles(instructional, candidate_urls, candidate_titles);
+                                        refresh_titles(instructional, "".to_string(), candidate_urls, candidate_titles, sender, job_sender);
                                     }
                                 });
                         });
@@ -700,7 +1041,9 @@ impl epi::App for App {
 
                     if ui.add(egui::ImageButton::new(*icons.get("mouse-line").unwrap(), (10.0, 10.0))).on_hover_text("Select title from a combobox").clicked() {
                         *use_title_combo=true;
-                        refresh_titles(instructional, candidate_urls, candidate_titles);
+                        // Whatever is typed in the title field is used to search.
+                        let query = instructional.title.to_string();
+                        refresh_titles(instructional, query, candidate_urls, candidate_titles, sender, job_sender);
                     }
                     ui.text_edit_singleline(&mut instructional.title);
                 }
@@ -716,20 +1059,44 @@ impl epi::App for App {
                 ui.add_sized(Vec2::new(ui.available_size().x - 100.0, ui.available_size().y) , egui::TextEdit::singleline(&mut instructional.url));
                 if ui.add(egui::ImageButton::new(*icons.get("download-cloud-line").unwrap(), (10.0, 10.0))).on_hover_text("Download timestamps").clicked() {
                     //Scrap instuctional info but try to retain things like associated files, labels etc
-                    instructional.timestamps = scrape_url(instructional.url.to_string());
+                    sender.send(Command::AddPendingTasks{tasks: 1}).expect("Failed to send AddPendingTasks command!");
+                    job_sender.send(Job::FetchMetadata{url: instructional.url.to_string()}).expect("Failed to send FetchMetadata command!");
                 } 
 
                 if !instructional.timestamps.is_empty() && ui.add(egui::ImageButton::new(*icons.get("arrow-down").unwrap(), (10.0, 10.0))).on_hover_text("Apply timestamps").clicked() {
-                    instructional.videos = extract_timestamps(instructional.timestamps.clone())
-                        .iter()
-                        .filter(|s| !s.is_empty())
-                        .enumerate()
-                        .map(|(i, s)| (i, s, if instructional.videos.len() > i { instructional.videos[i].file.clone() } else { format!("Volume{}.mp4", i + 1) }))
-                        .map(|(i, s, file) | Video {index: i + 1, file: file.clone(), scenes: s.iter().map(|s| Scene { index: s.index, title: s.title.clone(), start: s.start, end: s.end, file: file.clone(), text: "".to_string(), labels: s.labels.to_vec()}).collect(), duration: 0})
-                        .collect();
+                    *timestamps_preview = Some(parse_timestamps(&instructional.timestamps));
+                    // Don't throw away existing work by default.
+                    *merge_timestamps = instructional.videos.iter().any(|v| !v.scenes.is_empty());
+                    merge_kept_rows.clear();
                 }
             });
 
+            if !instructional.cover.is_empty() || !instructional.description.is_empty() || !instructional.tags.is_empty() {
+                egui::CollapsingHeader::new("Details").id_source(Id::new("details")).default_open(true).show(ui, |ui| {
+                    if !instructional.cover.is_empty() && cover_image.as_ref().map(|(url, _)| !url.eq(&instructional.cover)).unwrap_or(true) {
+                        let path = cover_img_path(&instructional.cover);
+                        let texture = if path.exists() { load_texture_id(frame, path.as_path()) } else { None };
+                        *cover_image = Some((instructional.cover.to_string(), texture));
+                    }
+                    ui.horizontal(|ui| {
+                        if let Some((_, Some(texture))) = cover_image {
+                            ui.image(*texture, (160.0, 160.0));
+                        }
+                        ui.vertical(|ui| {
+                            if !instructional.published_at.is_empty() {
+                                ui.label(format!("Published: {}", instructional.published_at.split('T').next().unwrap_or_default()));
+                            }
+                            if !instructional.tags.is_empty() {
+                                ui.label(format!("Tags: {}", instructional.tags.join(", ")));
+                            }
+                            egui::ScrollArea::vertical().max_height(160.0).show(ui, |ui| {
+                                ui.label(instructional.description.as_str());
+                            });
+                        });
+                    });
+                });
+            }
+
             if !instructional.timestamps.is_empty() {
                 egui::CollapsingHeader::new("Scraped text").id_source(Id::new("scraped-text")).default_open(false).show(ui, |ui| { 
                     egui::ScrollArea::vertical()
@@ -737,6 +1104,29 @@ impl epi::App for App {
                         .show(ui, |ui| {
                             ui.add_sized(Vec2::new(ui.available_size().x - 100.0, ui.available_size().y - 300.0) , egui::TextEdit::multiline(&mut instructional.timestamps));
                         });
+                    if parsed_timestamps.as_ref().map(|(text, _)| *text != instructional.timestamps).unwrap_or(true) {
+                        *parsed_timestamps = Some((instructional.timestamps.to_string(), parse_timestamps(&instructional.timestamps)));
+                    }
+                    let parsed = match parsed_timestamps {
+                        Some((_, parsed)) => parsed,
+                        None => return,
+                    };
+                    ui.label(format!("Found {} volume(s) with {} scene(s){}.", parsed.volumes.len(), parsed.volumes.iter().map(|v| v.len()).sum::<usize>(),
+                                     if parsed.explicit_volumes { "" } else { " (no volume headers, volumes guessed from start times)" }));
+                    parsed.volume_titles.iter().zip(parsed.volumes.iter()).for_each(|(title, scenes)| {
+                        ui.label(format!("{}: {} scene(s)", title, scenes.len()));
+                    });
+                    parsed.diagnostics.iter().for_each(|d| {
+                        let color = match d.kind {
+                            DiagnosticKind::CountMismatch => Color32::RED,
+                            _ => Color32::YELLOW,
+                        };
+                        if d.line == 0 {
+                            ui.colored_label(color, d.message.as_str());
+                        } else {
+                            ui.colored_label(color, format!("Line {}: {}: {}", d.line, d.message, d.text));
+                        }
+                    });
                 });
             }
 
@@ -749,9 +1139,29 @@ impl epi::App for App {
                     let s_index = 1;
                     let start = 0;
                     let end = video_duration(instructional.videos[v_index].file.to_string());
-                    let scene = Scene { index: s_index, title: "".to_string(), file: instructional.videos[v_index].file.clone(), text: "".to_string(), labels: vec![], start, end };
+                    let scene = Scene { index: s_index, title: "".to_string(), file: instructional.videos[v_index].file.clone(), text: "".to_string(), labels: vec![], start, end, speed: 1.0 };
                     sender.send(Command::AddScene{v_index, scene: scene.clone() }).expect("Failed to send AddScene command");
                     sender.send(Command::UpdateThumbnail{v_index, s_index, image: create_scene_image(&frame, instructional.creator.to_string(), instructional.title.to_string(), &scene)}).expect("Failed to send UpdateThumbnail command!");
+                    if instructional.creator.is_empty() && instructional.title.is_empty() {
+                        *identification_confidence = identify_instructional(instructional, candidate_urls, candidate_titles, &**provider, errors);
+                        *use_title_combo = !candidate_titles.is_empty();
+                    }
+                }
+                if !instructional.videos.is_empty() && icon_button(ui, icons, "search-line").on_hover_text("Identify the instructional and order the volumes using the video file names").clicked() {
+                    *identification_confidence = identify_instructional(instructional, candidate_urls, candidate_titles, &**provider, errors);
+                    *use_title_combo = !candidate_titles.is_empty();
+                    order_videos_by_volume(instructional, scene_images);
+                }
+                match identification_confidence {
+                    Some(confidence) if *confidence >= MIN_IDENTIFICATION_CONFIDENCE => { ui.label(format!("Identified with {:.0}% confidence", *confidence * 100.0)); },
+                    Some(confidence) => {
+                        ui.colored_label(Color32::YELLOW, format!("No confident match (best: {:.0}%)", *confidence * 100.0));
+                        if ui.button("Download products").on_hover_text("Download the products of the provider and identify the instructional again").clicked() {
+                            sender.send(Command::AddPendingTasks{tasks: 1}).expect("Failed to send AddPendingTasks command!");
+                            job_sender.send(Job::RefreshProvider{identify: true}).expect("Failed to send RefreshProvider command!");
+                        }
+                    },
+                    None => {},
                 }
             });
 
@@ -807,6 +1217,19 @@ impl epi::App for App {
                             }
                         }
                     });
+                    ui.horizontal(|ui| {
+                        let backend = general_settings.player.to_string();
+                        egui::ComboBox::from_label("Player").selected_text(general_settings.player.to_string()).show_ui(ui, |ui| {
+                            get_player_backends().into_iter().for_each(|b| {
+                                ui.selectable_value(&mut general_settings.player, b.to_string(), b);
+                            });
+                        });
+                        ui.label("Socket / address:");
+                        let changed = ui.text_edit_singleline(&mut general_settings.player_socket).on_hover_text("The mpv ipc socket or the VLC RC address (host:port). Leave empty to use one unique to this session.").changed();
+                        if changed || backend != general_settings.player {
+                            players.configure(&general_settings.player, &general_settings.player_socket);
+                        }
+                    });
                 });
 
                 egui::CollapsingHeader::new("Export Settings").id_source(Id::new("export")).default_open(false).show(ui, |ui| {
@@ -826,6 +1249,24 @@ impl epi::App for App {
                         ui.add_sized(Vec2::new(100.0, ui.available_size().y) , egui::TextEdit::singleline(&mut general_settings.playlist_export_filename));
                     });
                 });
+                egui::CollapsingHeader::new("Playback Settings").id_source(Id::new("playback")).default_open(false).show(ui, |ui| {
+                    ui.horizontal(|ui| {
+                        egui::ComboBox::from_label("When the scene ends").selected_text(match playback_options.mode {
+                            PlaybackMode::Continue => "Continue",
+                            PlaybackMode::SceneOnly => "Pause",
+                            PlaybackMode::Loop => "Loop",
+                        }).show_ui(ui, |ui| {
+                            ui.selectable_value(&mut playback_options.mode, PlaybackMode::Continue, "Continue");
+                            ui.selectable_value(&mut playback_options.mode, PlaybackMode::SceneOnly, "Pause");
+                            ui.selectable_value(&mut playback_options.mode, PlaybackMode::Loop, "Loop");
+                        });
+                        if playback_options.mode == PlaybackMode::Loop {
+                            ui.add(egui::Slider::new(&mut playback_options.loop_count, 0..=20).text("Repetitions")).on_hover_text("How many times to play the scene (0 loops forever).");
+                            ui.add(egui::Slider::new(&mut playback_options.loop_pause_secs, 0..=30).text("Pause between repetitions")).on_hover_text("Seconds to pause before playing the scene again.");
+                        }
+                        ui.label("Keys in mpv").on_hover_text("Ctrl+b: bookmark, Ctrl+a: scene start, Ctrl+e: scene end, Ctrl+n: note (type it and press enter).\nBookmarks and notes are added to the text of the scene that is playing.");
+                    });
+                });
                 egui::CollapsingHeader::new("Detection Settings").id_source(Id::new("detection")).default_open(false).show(ui, |ui| {
                     ui.horizontal(|ui| {
                         ui.add(egui::Slider::new(&mut detection_settings.threshold, 0.0..=1.0).text("Scene detection threshold")).on_hover_text("What percentage of changes pixels suggestes a scene change?");
@@ -834,42 +1275,175 @@ impl epi::App for App {
                     });
                 });
 
-                egui::CollapsingHeader::new("OCR Settings").id_source(Id::new("ocr")).default_open(false).show(ui, |ui| {
-                    ui.add(egui::Checkbox::new(&mut ocr_settings.grayscale, "Grayscle"));
+                egui::CollapsingHeader::new("Network Settings").id_source(Id::new("network")).default_open(false).show(ui, |ui| {
                     ui.horizontal(|ui| {
-                        ui.add(egui::Checkbox::new(&mut ocr_settings.gaussian_thresholding, "Gaussian Thresholding"));
-                        ui.add(egui::Slider::new(&mut ocr_settings.gaussian_thresholding_max_value, 0.0..=255.0));
-                        if ui.add(egui::Slider::new(&mut ocr_settings.gaussian_thresholding_blocksize, 1..=200)).changed() {
-                            if ocr_settings.gaussian_thresholding_blocksize % 2 == 0 {
-                                ocr_settings.gaussian_thresholding_blocksize += 1;
-                            }
-                        }
-                        ui.add(egui::Slider::new(&mut ocr_settings.gaussian_thresholding_c, -30.0..=30.0));
+                        ui.label("Base URL:");
+                        ui.text_edit_singleline(&mut http_settings.base_url).on_hover_text("The url of the store (or a local mirror) to scrape.");
+                        ui.label("User agent:");
+                        ui.text_edit_singleline(&mut http_settings.user_agent);
                     });
-
                     ui.horizontal(|ui| {
-                        ui.add(egui::Checkbox::new(&mut ocr_settings.otsu_thresholding, "Otsu Thresholding"));
-                        ui.add(egui::Slider::new(&mut ocr_settings.otsu_thresholding_min_value, 0.0..=255.0));
-                        ui.add(egui::Slider::new(&mut ocr_settings.otsu_thresholding_max_value, 0.0..=255.0));
+                        ui.add(egui::Slider::new(&mut http_settings.timeout_secs, 1..=120).text("Timeout (s)"));
+                        ui.add(egui::Slider::new(&mut http_settings.retries, 0..=10).text("Retries"));
+                        ui.add(egui::Slider::new(&mut http_settings.backoff_millis, 0..=10000).text("Backoff (ms)")).on_hover_text("The delay before the first retry. It's doubled on every retry.");
+                        ui.add(egui::Slider::new(&mut http_settings.min_interval_millis, 0..=10000).text("Min interval (ms)")).on_hover_text("The minimum time between two requests.");
                     });
-                    ui.add(egui::Checkbox::new(&mut ocr_settings.invert, "Invert"));
-
-
                     ui.horizontal(|ui| {
-                        ui.add(egui::Checkbox::new(&mut ocr_settings.denoise, "Denoise"));
-                        ui.add(egui::Slider::new(&mut ocr_settings.denoise_strength, 1.0..=100.0)).on_hover_text("Strength");
+                        if ui.button("Save").clicked() {
+                            save_http_settings(http_settings);
+                        }
+                        if ui.button("Reset").clicked() {
+                            *http_settings = HttpSettings::new();
+                        }
                     });
+                });
+
+                egui::CollapsingHeader::new("OCR Settings").id_source(Id::new("ocr")).default_open(false).show(ui, |ui| {
                     ui.horizontal(|ui| {
-                        ui.add(egui::Checkbox::new(&mut ocr_settings.erode, "Erode"));
-                        ui.add(egui::Slider::new(&mut ocr_settings.erode_kernel_size, 1..=10)).on_hover_text("Kernel size");
-                        ui.add(egui::Slider::new(&mut ocr_settings.erode_iterations, 1..=5)).on_hover_text("Iterations");
+                        egui::ComboBox::from_label("Preset")
+                            .selected_text(selected_ocr_preset.to_string())
+                            .show_ui(ui, |ui| {
+                                ocr_presets.iter().for_each(|p| {
+                                    if ui.selectable_value(selected_ocr_preset, p.name.to_string(), &p.name).clicked() {
+                                        *ocr_settings = p.settings.clone();
+                                        *ocr_preset_name = p.name.to_string();
+                                    }
+                                });
+                            });
+                        ui.add_sized(Vec2::new(150.0, ui.available_size().y), egui::TextEdit::singleline(ocr_preset_name)).on_hover_text("Preset name");
+                        if !ocr_preset_name.is_empty() && icon_button(ui, icons, "download-line").on_hover_text("Save preset").clicked() {
+                            // Keep the creators of the preset we are overwriting and associate it with the current creator.
+                            let mut creators = ocr_presets.iter().find(|p| p.name == *ocr_preset_name).map(|p| p.creators.clone()).unwrap_or_default();
+                            if !instructional.creator.is_empty() && !creators.contains(&instructional.creator) {
+                                creators.push(instructional.creator.to_string());
+                            }
+                            upsert_ocr_preset(ocr_presets, OcrPreset { name: ocr_preset_name.to_string(), creators, settings: ocr_settings.clone() });
+                            save_ocr_presets(ocr_presets);
+                            *selected_ocr_preset = ocr_preset_name.to_string();
+                        }
+                        if ocr_presets.iter().any(|p| p.name == *selected_ocr_preset) && icon_button(ui, icons, "delete-bin-line").on_hover_text("Delete preset").clicked() {
+                            ocr_presets.retain(|p| p.name != *selected_ocr_preset);
+                            save_ocr_presets(ocr_presets);
+                            *selected_ocr_preset = BLANK.to_owned();
+                        }
                     });
+                    ui.separator();
+                    ui.label("Preprocessing steps");
+                    // (step index, -1: move up, 1: move down, 0: remove)
+                    let mut step_action: Option<(usize, i32)> = None;
+                    let steps_len = ocr_settings.steps.len();
+                    for k in 0..steps_len {
+                        ui.horizontal(|ui| {
+                            ui.label(format!("{}. {}", k + 1, ocr_settings.steps[k].name()));
+                            match &mut ocr_settings.steps[k] {
+                                OcrStep::Grayscale | OcrStep::Invert => {},
+                                OcrStep::GaussianThresholding { max_value, blocksize, c } => {
+                                    ui.add(egui::Slider::new(max_value, 0.0..=255.0)).on_hover_text("Max value");
+                                    if ui.add(egui::Slider::new(blocksize, 3..=201)).on_hover_text("Block size").changed() && *blocksize % 2 == 0 {
+                                        *blocksize += 1;
+                                    }
+                                    ui.add(egui::Slider::new(c, -30.0..=30.0)).on_hover_text("C");
+                                },
+                                OcrStep::OtsuThresholding { min_value, max_value } => {
+                                    ui.add(egui::Slider::new(min_value, 0.0..=255.0)).on_hover_text("Min value");
+                                    ui.add(egui::Slider::new(max_value, 0.0..=255.0)).on_hover_text("Max value");
+                                },
+                                OcrStep::Denoise { strength } => {
+                                    ui.add(egui::Slider::new(strength, 1.0..=100.0)).on_hover_text("Strength");
+                                },
+                                OcrStep::Erode { kernel_size, iterations } | OcrStep::Dilate { kernel_size, iterations } => {
+                                    ui.add(egui::Slider::new(kernel_size, 1..=10)).on_hover_text("Kernel size");
+                                    ui.add(egui::Slider::new(iterations, 1..=5)).on_hover_text("Iterations");
+                                },
+                                OcrStep::Resize { scale } => {
+                                    ui.add(egui::Slider::new(scale, 0.25..=4.0)).on_hover_text("Scale");
+                                },
+                                OcrStep::Clahe { clip_limit, tile_size } => {
+                                    ui.add(egui::Slider::new(clip_limit, 0.5..=10.0)).on_hover_text("Clip limit");
+                                    ui.add(egui::Slider::new(tile_size, 1..=32)).on_hover_text("Tile size");
+                                },
+                                OcrStep::Sharpen { amount } => {
+                                    ui.add(egui::Slider::new(amount, 0.0..=5.0)).on_hover_text("Amount");
+                                },
+                                OcrStep::ColorKey { color, min_saturation, min_value } => {
+                                    egui::ComboBox::from_id_source(Id::new("ocr-key-color").with(k))
+                                        .selected_text(format!("{:?}", color))
+                                        .show_ui(ui, |ui| {
+                                            ui.selectable_value(color, KeyColor::Yellow, "Yellow");
+                                            ui.selectable_value(color, KeyColor::Red, "Red");
+                                            ui.selectable_value(color, KeyColor::White, "White");
+                                        });
+                                    ui.add(egui::Slider::new(min_saturation, 0.0..=255.0)).on_hover_text("Minimum saturation");
+                                    ui.add(egui::Slider::new(min_value, 0.0..=255.0)).on_hover_text("Minimum brightness");
+                                },
+                                OcrStep::Crop { x, y, width, height } => {
+                                    ui.add(egui::Slider::new(x, 0.0..=1.0)).on_hover_text("Left");
+                                    ui.add(egui::Slider::new(y, 0.0..=1.0)).on_hover_text("Top");
+                                    ui.add(egui::Slider::new(width, 0.0..=1.0)).on_hover_text("Width");
+                                    ui.add(egui::Slider::new(height, 0.0..=1.0)).on_hover_text("Height");
+                                },
+                            }
+                            if k > 0 && icon_button(ui, icons, "arrow-up").on_hover_text("Move step up").clicked() {
+                                step_action = Some((k, -1));
+                            }
+                            if k + 1 < steps_len && icon_button(ui, icons, "arrow-down").on_hover_text("Move step down").clicked() {
+                                step_action = Some((k, 1));
+                            }
+                            if icon_button(ui, icons, "close-line").on_hover_text("Remove step").clicked() {
+                                step_action = Some((k, 0));
+                            }
+                        });
+                    }
+                    match step_action {
+                        Some((k, -1)) => ocr_settings.steps.swap(k, k - 1),
+                        Some((k, 1)) => ocr_settings.steps.swap(k, k + 1),
+                        Some((k, _)) => { ocr_settings.steps.remove(k); },
+                        None => {},
+                    }
+                    egui::ComboBox::from_label("Add step")
+                        .selected_text("Select step")
+                        .show_ui(ui, |ui| {
+                            OcrStep::all().into_iter().for_each(|step| {
+                                if ui.selectable_label(false, step.name()).clicked() {
+                                    ocr_settings.steps.push(step);
+                                }
+                            });
+                        });
+                    ui.separator();
                     ui.horizontal(|ui| {
-                        ui.add(egui::Checkbox::new(&mut ocr_settings.dilate, "Dilate"));
-                        ui.add(egui::Slider::new(&mut ocr_settings.dilate_kernel_size, 1..=10)).on_hover_text("Kernel size");
-                        ui.add(egui::Slider::new(&mut ocr_settings.dilate_iterations, 1..=5)).on_hover_text("Iterations");
+                        ui.label("Language");
+                        ui.add_sized(Vec2::new(50.0, ui.available_size().y), egui::TextEdit::singleline(&mut ocr_settings.language)).on_hover_text("Tesseract language code (e.g. eng)");
                     });
                     ui.add(egui::Checkbox::new(&mut ocr_settings.spellcheking, "Spell check"));
+                    ui.separator();
+                    ui.add(egui::Checkbox::new(ocr_preview_enabled, "Live preview")).on_hover_text("Show the image of each preprocessing stage for the last scene that was recognized");
+                    if *ocr_preview_enabled {
+                        let preview_scene = ocr_preview_scene
+                            .filter(|(i, j)| instructional.videos.len() > *i && instructional.videos[*i].scenes.len() > *j)
+                            .or_else(|| instructional.videos.iter().enumerate()
+                                     .find(|(_, v)| !v.scenes.is_empty() && !v.file.is_empty())
+                                     .map(|(i, _)| (i, 0)));
+                        if let Some((i, j)) = preview_scene {
+                            let outdated = ocr_previewed.as_ref().map(|(key, settings)| *key != (i, j) || settings != ocr_settings).unwrap_or(true);
+                            if outdated && !*ocr_preview_pending {
+                                *ocr_preview_pending = true;
+                                job_sender.send(Job::PreviewOcr{ v_index: i, s_index: j, settings: ocr_settings.clone() }).expect("Failed to send PreviewOcr command!");
+                            }
+                            ui.label(format!("Previewing: {}", instructional.videos[i].scenes[j].title));
+                            ui.horizontal_wrapped(|ui| {
+                                ocr_preview_images.iter().for_each(|(name, texture)| {
+                                    ui.vertical(|ui| {
+                                        ui.label(name.as_str());
+                                        ui.image(*texture, egui::Vec2::new(192.0, 102.0));
+                                    });
+                                });
+                            });
+                        }
+                    }
+                    if !instructional.timestamps.is_empty() && ui.button("Auto-tune").on_hover_text("Search for the settings that best recognize the scraped titles").clicked() {
+                        sender.send(Command::AddPendingTasks{tasks: 1}).expect("Failed to send AddPendingTasks command!");
+                        job_sender.send(Job::AutoTuneOcr{ max_samples: 5 }).expect("Failed to send AutoTuneOcr command!");
+                    }
                 });
             });
 
@@ -939,7 +1513,7 @@ impl epi::App for App {
                                             let s_index = instructional.videos[i].scenes.len() + 1;
                                             let previous_end = if s_index >= 2 { instructional.videos[i].scenes[s_index - 2].end } else { 0 };
                                             let end = video_duration(instructional.videos[i].file.to_string());
-                                            let scene = Scene { index: s_index, title: "".to_string(), file: instructional.videos[i].file.clone(), text: "".to_string(), labels: vec![], start: previous_end, end };
+                                            let scene = Scene { index: s_index, title: "".to_string(), file: instructional.videos[i].file.clone(), text: "".to_string(), labels: vec![], start: previous_end, end, speed: 1.0 };
                                             sender.send(Command::AddScene{v_index: i, scene: scene.clone() }).expect("Failed to send AddScene command");
                                             sender.send(Command::UpdateThumbnail{v_index: i, s_index, image: create_scene_image(&frame, instructional.creator.to_string(), instructional.title.to_string(), &scene)}).expect("Failed to send UpdateThumbnail command!");
                                         }
@@ -993,6 +1567,14 @@ impl epi::App for App {
                                                 instructional.videos[i].scenes[k].title=instructional.videos[i].scenes[k - 1].title.to_string();
                                             }
                                         }
+                                        if icon_button(ui, icons, "character-recognition-line").on_hover_text("Detect all scene titles using OCR").clicked() {
+                                            recognize_titles(sender, job_sender, instructional, Some(i));
+                                        }
+                                        if !instructional.timestamps.is_empty() && icon_button(ui, icons, "split-cells-horizontal").on_hover_text("Reconcile scenes with scraped timestamps").clicked() {
+                                            let scraped = extract_timestamps(instructional.timestamps.clone());
+                                            let scraped_scenes = scraped.get(i).cloned().unwrap_or_default();
+                                            *reconciliation = Some((i, reconcile_scenes(&instructional.videos[i].scenes, &scraped_scenes)));
+                                        }
                                     });
 
                                     // Scenes - start
@@ -1132,32 +1714,47 @@ impl epi::App for App {
                                                 ui.vertical(|ui| {
                                                     ui.horizontal(|ui| {
                                                         let scene = instructional.videos[i].scenes[j].clone();
-                                                        let currently_playing = mpv_state.path.clone().map(|p| p == scene.file).unwrap_or(false);
+                                                        let player = players.main();
+                                                        let currently_playing = player.is_playing(&scene.file);
                                                         if !currently_playing {
-                                                          if ui.add(egui::ImageButton::new(*icons.get("play-line").unwrap(), (10.0, 10.0))).on_hover_text("Play Video").clicked() {
-                                                           mpv_stop(mpv_state);
-                                                            std::thread::spawn(move || {
-                                                              play_scene(scene);
-                                                            });
-                                                            watch_mpv(mpv_state, mpv_sender);
+                                                          let hover = match playback_options.for_range(scene.start, scene.end).mode {
+                                                              PlaybackMode::Continue => "Play Video",
+                                                              PlaybackMode::SceneOnly => "Play scene only",
+                                                              PlaybackMode::Loop => "Loop scene",
+                                                          };
+                                                          if icon_button(ui, icons, "play-line").on_hover_text(hover).clicked() {
+                                                            player.open(&scene.file, scene.start, scene.end, &PlaybackOptions { speed: scene.speed, ..*playback_options });
                                                           }
                                                         } else {
                                                           if ui.add(egui::ImageButton::new(*icons.get("pause-line").unwrap(), (10.0, 10.0))).on_hover_text("Pause Video").clicked() {
-                                                            let scene = instructional.videos[i].scenes[j].clone();
-                                                            std::thread::spawn(move || {
-                                                              mpv_pause();
-                                                            });
+                                                            player.pause();
                                                           }
                                                         }
-                                                        if mpv_state.running && mpv_state.playback_time > 0.0 && currently_playing {
+                                                        if icon_button(ui, icons, "split-cells-vertical").on_hover_text("Play scene in a second player, without interrupting the main one").clicked() {
+                                                            let options = PlaybackOptions { mode: PlaybackMode::SceneOnly, speed: scene.speed, ..PlaybackOptions::new() };
+                                                            players.preview().open(&scene.file, scene.start, scene.end, &options);
+                                                        }
+                                                        if icon_button(ui, icons, "add-circle-line").on_hover_text("Add to study session").clicked() {
+                                                            study_session.add(QueuedScene::new(instructional, &instructional.videos[i].scenes[j]));
+                                                            save_study_session(study_session);
+                                                        }
+                                                        if icon_button(ui, icons, "film-line").on_hover_text("Preview in the app").clicked() {
+                                                            if let Some((_, mut previous)) = preview.take() {
+                                                                previous.close(frame);
+                                                            }
+                                                            *preview = Some((scene.clone(), VideoPreview::new(&scene.file, scene.start as f64)));
+                                                        }
+                                                        let player = players.main();
+                                                        let player_state = player.state();
+                                                        if player_state.running && player_state.playback_time > 0.0 && currently_playing {
                                                             if ui.add(egui::ImageButton::new(*icons.get("rewind-mini-line").unwrap(), (10.0, 10.0))).on_hover_text("Set scene start").clicked() {
                                                                 let mut scene_to_update = instructional.videos[i].scenes[j].clone();
-                                                                scene_to_update.start = mpv_state.playback_time as usize;
+                                                                scene_to_update.start = player_state.playback_time as usize;
                                                                 instructional.videos[i].scenes[j]=scene_to_update;
 
                                                                 if j > 0 {
                                                                    let mut prev_scene_to_update = instructional.videos[i].scenes[j - 1].clone();
-                                                                   prev_scene_to_update.end = mpv_state.playback_time as usize;
+                                                                   prev_scene_to_update.end = player_state.playback_time as usize;
                                                                    instructional.videos[i].scenes[j - 1]=prev_scene_to_update;
                                                                 }
                                                             }
@@ -1166,10 +1763,10 @@ impl epi::App for App {
                                                                 let mut scene_to_update = instructional.videos[i].scenes[j].clone();
                                                                 let mut scene_to_add = instructional.videos[i].scenes[j].clone();
 
-                                                                scene_to_update.end = mpv_state.playback_time as usize;
+                                                                scene_to_update.end = player_state.playback_time as usize;
                                                                 instructional.videos[i].scenes[j]=scene_to_update;
 
-                                                                scene_to_add.start = mpv_state.playback_time as usize;
+                                                                scene_to_add.start = player_state.playback_time as usize;
                                                                 instructional.videos[i].scenes.insert(j + 1, scene_to_add);
 
                                                                 if scene_images.len() > i && scene_images[i].len() > j {
@@ -1179,19 +1776,43 @@ impl epi::App for App {
                                                             }
                                                             if ui.add(egui::ImageButton::new(*icons.get("speed-mini-fill").unwrap(), (10.0, 10.0))).on_hover_text("Set scene end").clicked() {
                                                                 let mut scene_to_update = instructional.videos[i].scenes[j].clone();
-                                                                scene_to_update.end = mpv_state.playback_time as usize;
+                                                                scene_to_update.end = player_state.playback_time as usize;
                                                                 instructional.videos[i].scenes[j]=scene_to_update;
 
                                                                 if j + 1 < instructional.videos[i].scenes.len() {
                                                                    let mut next_scene_to_update = instructional.videos[i].scenes[j + 1].clone();
-                                                                   next_scene_to_update.start = mpv_state.playback_time as usize;
+                                                                   next_scene_to_update.start = player_state.playback_time as usize;
                                                                    instructional.videos[i].scenes[j + 1]=next_scene_to_update;
                                                                 }
                                                             }
+                                                            ui.separator();
+                                                            if ui.small_button("-5s").on_hover_text("Rewind 5 seconds").clicked() {
+                                                                player.rewind(5.0);
+                                                            }
+                                                            if ui.small_button("|<").on_hover_text("Previous frame").clicked() {
+                                                                player.frame_step(false);
+                                                            }
+                                                            if ui.small_button(">|").on_hover_text("Next frame").clicked() {
+                                                                player.frame_step(true);
+                                                            }
+                                                            let speed = instructional.videos[i].scenes[j].speed;
+                                                            if ui.small_button("-").on_hover_text("Slower (remembered for the scene)").clicked() {
+                                                                instructional.videos[i].scenes[j].speed = next_speed(speed, false);
+                                                                player.set_speed(instructional.videos[i].scenes[j].speed);
+                                                            }
+                                                            if ui.small_button(format!("{}x", speed)).on_hover_text("Reset speed").clicked() {
+                                                                instructional.videos[i].scenes[j].speed = 1.0;
+                                                                player.set_speed(1.0);
+                                                            }
+                                                            if ui.small_button("+").on_hover_text("Faster (remembered for the scene)").clicked() {
+                                                                instructional.videos[i].scenes[j].speed = next_speed(speed, true);
+                                                                player.set_speed(instructional.videos[i].scenes[j].speed);
+                                                            }
                                                         }
                                                     });
                                                     if ui.add(egui::ImageButton::new(*icons.get("character-recognition-line").unwrap(), (10.0, 10.0))).on_hover_text("Detect scene title using OCR").clicked() {
                                                         let scene = &instructional.videos[i].scenes[j];
+                                                        *ocr_preview_scene = Some((i, j));
                                                         if let Some(text) = scene_text_with_settings(instructional.creator.to_string(), instructional.title.to_string(), scene, &general_settings, &ocr_settings) {
                                                             job_sender.send(Job::CreateThumbnail{ v_index: i, s_index: j, imageFn: create_ocr_image}).expect("Failed to send CreateThumbnail command!");
                                                             instructional.videos[i].scenes[j].title =  text;
@@ -1200,9 +1821,20 @@ impl epi::App for App {
                                                 });
                                             }
                                         });
+                                        if let Some((_, _, progress)) = now_playing.filter(|(pi, pj, _)| *pi == i && *pj == j) {
+                                            ui.add(egui::ProgressBar::new(progress).show_percentage());
+                                        }
                                         //end of drop target
                                         ui.separator();
                                     });                                    
+
+                                    if now_playing.map(|(pi, pj, _)| pi == i && pj == j).unwrap_or(false) {
+                                        ui.painter().rect_stroke(drop.response.rect, 2.0, ui.visuals().selection.stroke);
+                                        if *scrolled_to_playing != Some((i, j)) {
+                                            drop.response.scroll_to_me(Align::Center);
+                                            *scrolled_to_playing = Some((i, j));
+                                        }
+                                    }
                                         
                                     let is_being_dragged = ui.memory().is_anything_being_dragged();
                                     if is_being_dragged && drop.response.hovered() {
@@ -1239,7 +1871,38 @@ impl epi::App for App {
                         jobs.push(job);
                     }
 
-                    update_mpv_state(mpv_state, mpv_recv);
+                    // Bookmarks, notes and scene boundaries captured from inside the player.
+                    for event in players.update() {
+                        match apply_player_action(instructional, &event.path, event.playback_time, &event.action) {
+                            Some((v_index, s_index)) if event.action == PlayerAction::SceneStart || event.action == PlayerAction::SceneEnd => {
+                                sender.send(Command::AddPendingTasks{tasks: 1}).expect("Failed to send AddPendingTasks command!");
+                                job_sender.send(Job::CreateThumbnail{ v_index, s_index, imageFn: create_scene_image}).expect("Failed to send CreateThumbnail command!");
+                            },
+                            Some(_) => (),
+                            None => println!("No scene found at {} of: {}", seconds_to_time(event.playback_time as usize), event.path),
+                        }
+                    }
+
+                    if *study_session_playing {
+                        let state = players.main().state().clone();
+                        let reached_end = state.path.map(|path| study_session.track(&path, state.playback_time, state.duration)).unwrap_or(false);
+                        let closed = *study_session_player_running && !state.running;
+                        *study_session_player_running = state.running;
+                        if closed {
+                            // The player was closed.
+                            *study_session_playing = false;
+                            *study_session_saved_position = study_session.position;
+                            save_study_session(study_session);
+                        } else if reached_end {
+                            study_session.next();
+                            play_study_session(players, study_session, study_session_playing, study_session_player_running);
+                            save_study_session(study_session);
+                        } else if (study_session.position - *study_session_saved_position).abs() >= 5.0 {
+                            // Remember the position, in case the session is interrupted.
+                            *study_session_saved_position = study_session.position;
+                            save_study_session(study_session);
+                        }
+                    }
 
                     if !commands.is_empty() {
                         commands.into_iter().for_each(|command| {
@@ -1274,11 +1937,99 @@ impl epi::App for App {
                                     save_org_in_dir(instructional, filename);
                                 },
                                 Command::ExportMarkdown {filename} => {
-                                    save_md_in_dir(instructional, filename);
+                                    save_md_in_dir(instructional, filename, errors);
                                 }
                                 Command::AddPendingTasks {tasks} => {
                                     *total_tasks += tasks as f32;
                                 }
+                                Command::ProposeTitle {v_index, s_index, title, image} => {
+                                    match title {
+                                        Some(proposed) if instructional.videos.len() > v_index && instructional.videos[v_index].scenes.len() > s_index => {
+                                            // A new proposal for the same scene replaces the previous one.
+                                            title_proposals.iter()
+                                                .filter(|p| p.v_index == v_index && p.s_index == s_index)
+                                                .filter_map(|p| p.image)
+                                                .for_each(|t| frame.free_texture(t));
+                                            title_proposals.retain(|p| p.v_index != v_index || p.s_index != s_index);
+                                            let current = instructional.videos[v_index].scenes[s_index].title.to_string();
+                                            title_proposals.push(TitleProposal { v_index, s_index, current, proposed, image, selected: true });
+                                        },
+                                        _ => image.iter().for_each(|t| frame.free_texture(*t)),
+                                    }
+                                    *completed_tasks += 1.0;
+                                    *progress =  *completed_tasks / *total_tasks;
+                                },
+                                Command::UpdateOcrPreview {v_index, s_index, settings, images} => {
+                                    ocr_preview_images.iter().for_each(|(_, t)| frame.free_texture(*t));
+                                    *ocr_preview_images = images;
+                                    *ocr_previewed = Some(((v_index, s_index), settings));
+                                    *ocr_preview_pending = false;
+                                },
+                                Command::ApplyOcrSettings {settings, score} => {
+                                    println!("Applying auto-tuned OCR settings with score: {:.3}", score);
+                                    *ocr_settings = settings;
+                                    *selected_ocr_preset = BLANK.to_owned();
+                                    *ocr_preset_name = format!("{} (auto-tuned)", instructional.creator).trim().to_string();
+                                    *completed_tasks += 1.0;
+                                    *progress =  *completed_tasks / *total_tasks;
+                                },
+                                Command::UpdateMetadata {url, timestamps, details, errors: fetch_errors} => {
+                                    // Ignore the results if another url was entered in the meantime.
+                                    if instructional.url == url {
+                                        if let Some(timestamps) = timestamps {
+                                            instructional.timestamps = timestamps;
+                                        }
+                                        if let Some(details) = details {
+                                            instructional.description = details.description;
+                                            instructional.published_at = details.published_at;
+                                            instructional.tags = details.tags;
+                                            instructional.cover = details.cover;
+                                            // Reload the texture on the next frame.
+                                            *cover_image = None;
+                                        }
+                                    }
+                                    errors.extend(fetch_errors);
+                                    *completed_tasks += 1.0;
+                                    *progress =  *completed_tasks / *total_tasks;
+                                },
+                                Command::UpdateTitleCandidates {creator, results} => {
+                                    match results {
+                                        Ok(results) if instructional.creator == creator => {
+                                            *candidate_titles = results.iter().take(MAX_TITLE_CANDIDATES).map(|r| r.title.to_string()).collect();
+                                            *candidate_urls = results.iter().take(MAX_TITLE_CANDIDATES).map(|r| r.url.to_string()).collect();
+                                        },
+                                        Ok(_) => {},
+                                        Err(e) => errors.push(e),
+                                    }
+                                    *completed_tasks += 1.0;
+                                    *progress =  *completed_tasks / *total_tasks;
+                                },
+                                Command::ProductCacheUpdated {result} => {
+                                    match result {
+                                        Ok(_) => *candidate_creators = provider.list_creators(),
+                                        Err(e) => errors.push(e),
+                                    }
+                                    if cache_status.is_some() {
+                                        *cache_status = Some(get_cache_status());
+                                    }
+                                    *completed_tasks += 1.0;
+                                    *progress =  *completed_tasks / *total_tasks;
+                                },
+                                Command::ProviderRefreshed {result, identify} => {
+                                    match result {
+                                        Ok(_) if identify && !instructional.videos.is_empty() => {
+                                            *identification_confidence = identify_instructional(instructional, candidate_urls, candidate_titles, &**provider, errors);
+                                            *use_title_combo = !candidate_titles.is_empty();
+                                        },
+                                        Ok(_) => {},
+                                        Err(e) => errors.push(e),
+                                    }
+                                    if cache_status.is_some() {
+                                        *cache_status = Some(get_cache_status());
+                                    }
+                                    *completed_tasks += 1.0;
+                                    *progress =  *completed_tasks / *total_tasks;
+                                }
                             } 
                         });
                     }
@@ -1290,6 +2041,10 @@ impl epi::App for App {
                             let instructional = instructional.clone();
                             let sender = sender.clone();
                             let detection_settings = detection_settings.clone();
+                            let general_settings = general_settings.clone();
+                            let ocr_settings = ocr_settings.clone();
+                            let provider = provider.clone();
+                            let http_settings = http_settings.clone();
                             let chunk_jobs = chunk.collect::<Vec<Job>>();
                             std::thread::spawn(move || {
                                 chunk_jobs.into_iter().for_each(|job| {
@@ -1316,7 +2071,7 @@ impl epi::App for App {
                                                 })
                                                 .filter(|(t, n)| (*t as i32 - *n as i32).abs() > detection_settings.minimum_length) // filter very short scenes
                                                 .enumerate() // (i, (t, nt))
-                                                .map(| (si, (t, nt)) | (si, Scene {index: si, title: format!("Scene {}: {} - {}", si+1, t, nt), text: "".to_string(), labels: vec![], file: file.clone(), start: t + detection_settings.offset, end: if nt != 0 { nt + detection_settings.offset } else { nt }}))
+                                                .map(| (si, (t, nt)) | (si, Scene {index: si, title: format!("Scene {}: {} - {}", si+1, t, nt), text: "".to_string(), labels: vec![], file: file.clone(), start: t + detection_settings.offset, end: if nt != 0 { nt + detection_settings.offset } else { nt }, speed: 1.0}))
                                                 .for_each(|(s_index, scene)| {
                                                     sender.send(Command::AddScene{v_index, scene: scene.to_owned()}).expect("Failed to send AddScene command");
                                                     sender.send(Command::UpdateThumbnail{v_index, s_index, image: create_scene_image(&frame, instructional.creator.to_string(), instructional.title.to_string(), &scene)}).expect("Failed to send UpdateThumbnail command!");
@@ -1326,6 +2081,50 @@ impl epi::App for App {
                                             let scene = instructional.videos[v_index].scenes[s_index].clone();
                                             sender.send(Command::UpdateThumbnail{v_index, s_index, image: imageFn(&frame, instructional.creator.to_string(), instructional.title.to_string(), &scene)}).expect("Failed to send UpdateThumbnail command!");
                                         },
+                                        Job::RecognizeTitle {v_index, s_index} => {
+                                            let scene = instructional.videos[v_index].scenes[s_index].clone();
+                                            let title = scene_text_with_settings(instructional.creator.to_string(), instructional.title.to_string(), &scene, &general_settings, &ocr_settings);
+                                            let image = title.as_ref().and_then(|_| create_ocr_image(&frame, instructional.creator.to_string(), instructional.title.to_string(), &scene));
+                                            sender.send(Command::ProposeTitle{v_index, s_index, title, image}).expect("Failed to send ProposeTitle command!");
+                                        },
+                                        Job::PreviewOcr {v_index, s_index, settings} => {
+                                            let scene = instructional.videos[v_index].scenes[s_index].clone();
+                                            let images = create_ocr_preview_images(&frame, instructional.creator.to_string(), instructional.title.to_string(), &scene, &settings);
+                                            sender.send(Command::UpdateOcrPreview{v_index, s_index, settings, images}).expect("Failed to send UpdateOcrPreview command!");
+                                            frame.request_repaint();
+                                        },
+                                        Job::AutoTuneOcr {max_samples} => {
+                                            let samples = autotune_samples(&instructional, max_samples);
+                                            let (settings, score) = autotune_ocr(instructional.creator.to_string(), instructional.title.to_string(), &samples, &ocr_settings, &general_settings)
+                                                .unwrap_or((ocr_settings.clone(), 0.0));
+                                            sender.send(Command::ApplyOcrSettings{settings, score}).expect("Failed to send ApplyOcrSettings command!");
+                                        },
+                                        Job::FetchMetadata {url} => {
+                                            let mut fetch_errors = vec![];
+                                            let timestamps = provider.fetch_chapters(url.to_string()).map_err(|e| fetch_errors.push(e)).ok();
+                                            let details = provider.fetch_details(url.to_string()).map_err(|e| fetch_errors.push(e)).ok().flatten();
+                                            if let Some(cover) = details.as_ref().map(|d| d.cover.to_string()).filter(|c| !c.is_empty()) {
+                                                if let Err(e) = provider.download_cover(cover) {
+                                                    fetch_errors.push(e);
+                                                }
+                                            }
+                                            sender.send(Command::UpdateMetadata{url, timestamps, details, errors: fetch_errors}).expect("Failed to send UpdateMetadata command!");
+                                            frame.request_repaint();
+                                        },
+                                        Job::SearchTitles {creator, query} => {
+                                            let results = provider.search_products(creator.to_string(), query);
+                                            sender.send(Command::UpdateTitleCandidates{creator, results}).expect("Failed to send UpdateTitleCandidates command!");
+                                            frame.request_repaint();
+                                        },
+                                        Job::RefreshProvider {identify} => {
+                                            sender.send(Command::ProviderRefreshed{result: provider.refresh(), identify}).expect("Failed to send ProviderRefreshed command!");
+                                            frame.request_repaint();
+                                        },
+                                        Job::UpdateProductCache {full} => {
+                                            let result = HttpClient::new(&http_settings).and_then(|client| update_cache(&client, full)).map(|_| ()).map_err(|e| e.to_string());
+                                            sender.send(Command::ProductCacheUpdated{result}).expect("Failed to send ProductCacheUpdated command!");
+                                            frame.request_repaint();
+                                        },
                                     }
                                 });
                             });
@@ -1334,6 +2133,497 @@ impl epi::App for App {
                 });
         });
 
+        if !title_proposals.is_empty() {
+            let mut open = true;
+            // (accept, selected only)
+            let mut review_action: Option<(bool, bool)> = None;
+            egui::Window::new("OCR review").open(&mut open).default_width(800.0).show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    if ui.button("Accept all").clicked() {
+                        review_action = Some((true, false));
+                    }
+                    if ui.button("Accept selected").clicked() {
+                        review_action = Some((true, true));
+                    }
+                    if ui.button("Reject selected").clicked() {
+                        review_action = Some((false, true));
+                    }
+                    if ui.button("Reject all").clicked() {
+                        review_action = Some((false, false));
+                    }
+                });
+                ui.separator();
+                egui::ScrollArea::vertical()
+                    .max_height(600.0)
+                    .show(ui, |ui| {
+                        egui::Grid::new("ocr-review-grid").striped(true).show(ui, |ui| {
+                            ui.label("");
+                            ui.label("Scene");
+                            ui.label("Preprocessed");
+                            ui.label("Current title");
+                            ui.label("Proposed title");
+                            ui.end_row();
+                            title_proposals.iter_mut().for_each(|p| {
+                                ui.checkbox(&mut p.selected, "");
+                                let size = egui::Vec2::new(192.0, 102.0);
+                                match scene_images.get(p.v_index).and_then(|v| v.get(p.s_index)).cloned().flatten() {
+                                    Some(img) => { ui.image(img, size); },
+                                    None => { ui.label(format!("{}/{}", p.v_index + 1, p.s_index + 1)); },
+                                }
+                                match p.image {
+                                    Some(img) => { ui.image(img, size); },
+                                    None => { ui.label(""); },
+                                }
+                                ui.label(p.current.as_str());
+                                ui.text_edit_singleline(&mut p.proposed);
+                                ui.end_row();
+                            });
+                        });
+                    });
+            });
+
+            match review_action {
+                Some((accept, selected_only)) => {
+                    title_proposals.iter()
+                        .filter(|p| accept && (!selected_only || p.selected))
+                        .filter(|p| instructional.videos.len() > p.v_index && instructional.videos[p.v_index].scenes.len() > p.s_index)
+                        .for_each(|p| instructional.videos[p.v_index].scenes[p.s_index].title = p.proposed.to_string());
+                    title_proposals.iter()
+                        .filter(|p| !selected_only || p.selected)
+                        .filter_map(|p| p.image)
+                        .for_each(|t| frame.free_texture(t));
+                    title_proposals.retain(|p| selected_only && !p.selected);
+                },
+                None => {},
+            }
+            if !open {
+                title_proposals.iter().filter_map(|p| p.image).for_each(|t| frame.free_texture(t));
+                title_proposals.clear();
+            }
+        }
+
+        if let Some(text) = pasted_chapters {
+            let mut open = true;
+            let mut import = false;
+            egui::Window::new("Import chapters").open(&mut open).default_width(600.0).show(ctx, |ui| {
+                ui.label("Paste a chapter list (e.g. from a course or video description):");
+                egui::ScrollArea::vertical()
+                    .max_height(400.0)
+                    .show(ui, |ui| {
+                        ui.add_sized(Vec2::new(ui.available_size().x, 300.0), egui::TextEdit::multiline(text));
+                    });
+                if ui.button("Import").clicked() {
+                    import = true;
+                }
+            });
+
+            if import {
+                instructional.timestamps = chapters_from_text(text);
+                *timestamps_preview = Some(parse_timestamps(&instructional.timestamps));
+                *merge_timestamps = instructional.videos.iter().any(|v| !v.scenes.is_empty());
+                merge_kept_rows.clear();
+            }
+            if import || !open {
+                *pasted_chapters = None;
+            }
+        }
+
+        if let Some(parsed) = timestamps_preview {
+            let mut open = true;
+            let mut apply = false;
+            // Empty volumes are skipped when applying.
+            let volumes: Vec<(String, Vec<Scene>)> = parsed.volume_titles.iter().zip(parsed.volumes.iter())
+                .filter(|(_, scenes)| !scenes.is_empty())
+                .map(|(title, scenes)| (title.to_string(), scenes.to_vec()))
+                .collect();
+            egui::Window::new("Apply timestamps").open(&mut open).default_width(600.0).show(ctx, |ui| {
+                if parsed.explicit_volumes {
+                    ui.label("Volumes were split using the volume headers.");
+                } else {
+                    ui.colored_label(Color32::YELLOW, "No volume headers found. Volumes were guessed from the start times.");
+                }
+                ui.horizontal(|ui| {
+                    ui.checkbox(merge_timestamps, "Merge into existing scenes").on_hover_text("Keep notes, labels and scenes that were not scraped");
+                    if *merge_timestamps {
+                        ui.checkbox(&mut merge_options.update_timings, "Update timings");
+                        ui.checkbox(&mut merge_options.update_titles, "Update titles");
+                        ui.checkbox(&mut merge_options.add_new_scenes, "Add new scenes");
+                    }
+                });
+                if ui.button("Apply").clicked() {
+                    apply = true;
+                }
+                ui.separator();
+                egui::ScrollArea::vertical()
+                    .max_height(600.0)
+                    .show(ui, |ui| {
+                        volumes.iter().enumerate().for_each(|(i, (title, scenes))| {
+                            let file = if instructional.videos.len() > i { instructional.videos[i].file.clone() } else { format!("Volume{}.mp4", i + 1) };
+                            egui::CollapsingHeader::new(format!("{} ({} scenes) -> {}", title, scenes.len(), file)).id_source(Id::new("timestamps-preview").with(i)).default_open(true).show(ui, |ui| {
+                                if *merge_timestamps && instructional.videos.len() > i {
+                                    let merged = merge_scenes(&instructional.videos[i].scenes, scenes, merge_options);
+                                    let changes: Vec<(usize, &MergedScene)> = merged.iter().enumerate().filter(|(_, m)| m.is_changed()).collect();
+                                    ui.label(format!("{} change(s), {} scene(s) unchanged.", changes.len(), merged.iter().filter(|m| m.after.is_some()).count() - changes.len()));
+                                    egui::Grid::new(Id::new("timestamps-diff").with(i)).striped(true).show(ui, |ui| {
+                                        changes.iter().for_each(|(row, m)| {
+                                            let mut replace = !merge_kept_rows.contains(&(i, *row));
+                                            let label = if m.before.is_none() { "Add" } else { "Replace" };
+                                            if ui.checkbox(&mut replace, label).changed() {
+                                                if replace {
+                                                    merge_kept_rows.retain(|r| *r != (i, *row));
+                                                } else {
+                                                    merge_kept_rows.push((i, *row));
+                                                }
+                                            }
+                                            ui.label(m.before.as_ref().map(|s| format!("{} - {}: {}", seconds_to_time(s.start), seconds_to_time(s.end), s.title)).unwrap_or("-".to_string()));
+                                            ui.label("->");
+                                            let after = m.after.as_ref().map(|s| format!("{} - {}: {}", seconds_to_time(s.start), seconds_to_time(s.end), s.title)).unwrap_or_default();
+                                            if m.before.is_none() {
+                                                ui.colored_label(Color32::GREEN, after);
+                                            } else {
+                                                ui.colored_label(Color32::YELLOW, after);
+                                            }
+                                            ui.end_row();
+                                        });
+                                    });
+                                } else {
+                                    scenes.iter().for_each(|s| {
+                                        ui.label(format!("{} - {}: {}", seconds_to_time(s.start), seconds_to_time(s.end), s.title));
+                                    });
+                                }
+                            });
+                        });
+                    });
+            });
+
+            if apply && *merge_timestamps {
+                for (i, (_, scenes)) in volumes.iter().enumerate() {
+                    if instructional.videos.len() <= i {
+                        let file = format!("Volume{}.mp4", i + 1);
+                        instructional.videos.push(Video {index: i + 1, file: file.clone(), scenes: scenes.iter().map(|s| Scene { file: file.clone(), ..s.clone() }).collect(), duration: 0});
+                        *scene_images = reallocate_scene_images(frame, &instructional.videos, i, scene_images.clone());
+                        sender.send(Command::AddPendingTasks{tasks: scenes.len()}).expect("Failed to send AddPendingTasks command!");
+                        for j in 0..scenes.len() {
+                            job_sender.send(Job::CreateThumbnail{ v_index: i, s_index: j, imageFn: create_scene_image}).expect("Failed to send CreateThumbnail command!");
+                        }
+                        continue;
+                    }
+                    let mut merged = merge_scenes(&instructional.videos[i].scenes, scenes, merge_options);
+                    let kept: Vec<usize> = merge_kept_rows.iter().filter(|(v, _)| *v == i).map(|(_, row)| *row).collect();
+                    keep_existing(&mut merged, &kept);
+                    let merged = apply_merge(&merged, &instructional.videos[i].file);
+                    // Thumbnails of scenes that still start at the same time are kept.
+                    let old_scenes = instructional.videos[i].scenes.clone();
+                    let old_images = scene_images.get(i).cloned().unwrap_or_default();
+                    let images: Vec<Option<TextureId>> = merged.iter()
+                        .map(|(existing, scene)| existing.filter(|e| old_scenes[*e].start == scene.start).and_then(|e| old_images.get(e).cloned().flatten()))
+                        .collect();
+                    instructional.videos[i].scenes = merged.into_iter().map(|(_, scene)| scene).collect();
+                    *scene_images = reallocate_scene_images(frame, &instructional.videos, i, scene_images.clone());
+                    let missing: Vec<usize> = images.iter().enumerate().filter(|(_, image)| image.is_none()).map(|(j, _)| j).collect();
+                    if i < scene_images.len() {
+                        scene_images[i] = images;
+                    }
+                    sender.send(Command::AddPendingTasks{tasks: missing.len()}).expect("Failed to send AddPendingTasks command!");
+                    for j in missing {
+                        job_sender.send(Job::CreateThumbnail{ v_index: i, s_index: j, imageFn: create_scene_image}).expect("Failed to send CreateThumbnail command!");
+                    }
+                }
+            } else if apply {
+                instructional.videos = volumes
+                    .iter()
+                    .enumerate()
+                    .map(|(i, (_, s))| (i, s, if instructional.videos.len() > i { instructional.videos[i].file.clone() } else { format!("Volume{}.mp4", i + 1) }))
+                    .map(|(i, s, file) | Video {index: i + 1, file: file.clone(), scenes: s.iter().map(|s| Scene { index: s.index, title: s.title.clone(), start: s.start, end: s.end, file: file.clone(), text: "".to_string(), labels: s.labels.to_vec(), speed: 1.0}).collect(), duration: 0})
+                    .collect();
+            }
+            if apply || !open {
+                *timestamps_preview = None;
+                merge_kept_rows.clear();
+            }
+        }
+
+        if *show_study_session {
+            let mut action: Option<SessionAction> = None;
+            let mut selected: Option<usize> = None;
+            let mut removed: Option<usize> = None;
+            let mut swapped: Option<(usize, usize)> = None;
+            egui::Window::new("Study session").open(show_study_session).default_width(500.0).show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    let resume = if study_session.position > 0.0 { "Resume" } else { "Play" };
+                    if ui.add_enabled(!study_session.is_finished(), egui::Button::new(resume)).clicked() {
+                        action = Some(SessionAction::Play);
+                    }
+                    if ui.button("Previous").clicked() {
+                        action = Some(SessionAction::Previous);
+                    }
+                    if ui.button("Next").on_hover_text("Mark the scene as watched and play the next one").clicked() {
+                        action = Some(SessionAction::Next);
+                    }
+                    if ui.button("Skip").on_hover_text("Play the next scene without marking this one as watched").clicked() {
+                        action = Some(SessionAction::Skip);
+                    }
+                    if *study_session_playing && ui.button("Stop").clicked() {
+                        action = Some(SessionAction::Stop);
+                    }
+                    if ui.button("Clear").clicked() {
+                        action = Some(SessionAction::Clear);
+                    }
+                });
+                ui.label(format!("{} scene(s), {} watched.", study_session.scenes.len(), study_session.scenes.iter().filter(|s| s.watched).count()));
+                ui.separator();
+                egui::ScrollArea::vertical()
+                    .max_height(400.0)
+                    .show(ui, |ui| {
+                        egui::Grid::new("study-session-grid").striped(true).show(ui, |ui| {
+                            study_session.scenes.iter().enumerate().for_each(|(index, scene)| {
+                                let text = format!("{}. {} ({} - {}) {}", index + 1, scene.title, seconds_to_time(scene.start), seconds_to_time(scene.end), if scene.watched { "(watched)" } else { "" });
+                                if ui.selectable_label(index == study_session.current, text).on_hover_text(scene.instructional.as_str()).clicked() {
+                                    selected = Some(index);
+                                }
+                                if index > 0 && icon_button(ui, icons, "arrow-up").on_hover_text("Move up").clicked() {
+                                    swapped = Some((index - 1, index));
+                                }
+                                if index + 1 < study_session.scenes.len() && icon_button(ui, icons, "arrow-down").on_hover_text("Move down").clicked() {
+                                    swapped = Some((index, index + 1));
+                                }
+                                if icon_button(ui, icons, "delete-bin-line").on_hover_text("Remove").clicked() {
+                                    removed = Some(index);
+                                }
+                                ui.end_row();
+                            });
+                        });
+                    });
+            });
+
+            if let Some(index) = selected {
+                study_session.select(index);
+                action = Some(SessionAction::Play);
+            }
+            if let Some((a, b)) = swapped {
+                study_session.swap(a, b);
+            }
+            if let Some(index) = removed {
+                study_session.remove(index);
+            }
+            match action {
+                Some(SessionAction::Play) => play_study_session(players, study_session, study_session_playing, study_session_player_running),
+                Some(SessionAction::Previous) => {
+                    study_session.previous();
+                    play_study_session(players, study_session, study_session_playing, study_session_player_running);
+                },
+                Some(SessionAction::Next) => {
+                    study_session.next();
+                    play_study_session(players, study_session, study_session_playing, study_session_player_running);
+                },
+                Some(SessionAction::Skip) => {
+                    study_session.skip();
+                    play_study_session(players, study_session, study_session_playing, study_session_player_running);
+                },
+                Some(SessionAction::Stop) => {
+                    *study_session_playing = false;
+                    players.main().stop();
+                },
+                Some(SessionAction::Clear) => {
+                    if *study_session_playing {
+                        players.main().stop();
+                    }
+                    *study_session = StudySession::new();
+                    *study_session_playing = false;
+                },
+                None => (),
+            }
+            if action.is_some() || selected.is_some() || swapped.is_some() || removed.is_some() {
+                *study_session_saved_position = study_session.position;
+                save_study_session(study_session);
+            }
+        }
+
+        if let Some((scene, video_preview)) = preview {
+            let mut open = true;
+            let mut refresh_needed: Option<(usize, usize)> = None;
+            let found = find_scene(instructional, &scene.file, scene.start, scene.end);
+            video_preview.update(frame);
+            if video_preview.error.is_none() && (video_preview.texture.is_none() || video_preview.is_pending()) {
+                // Show the frame as soon as it's decoded.
+                ctx.request_repaint();
+            }
+            let end = if scene.end > scene.start { scene.end as f64 } else { video_preview.duration.max(scene.start as f64 + 1.0) };
+            egui::Window::new(format!("Preview: {}", scene.title)).id(Id::new("preview")).open(&mut open).show(ctx, |ui| {
+                match video_preview.texture {
+                    Some((texture, size)) => { ui.image(texture, Vec2::new(size[0] as f32, size[1] as f32)); },
+                    None => { ui.label("Decoding..."); },
+                }
+                if let Some(error) = &video_preview.error {
+                    ui.colored_label(Color32::RED, error);
+                }
+                let mut position = video_preview.position;
+                if ui.add(egui::Slider::new(&mut position, scene.start as f64..=end).show_value(false)).changed() {
+                    video_preview.request(position);
+                }
+                ui.horizontal(|ui| {
+                    if ui.small_button("|<").on_hover_text("Previous frame").clicked() {
+                        video_preview.step(-1);
+                    }
+                    if ui.small_button(">|").on_hover_text("Next frame").clicked() {
+                        video_preview.step(1);
+                    }
+                    ui.label(format!("{} ({:.3}s)", seconds_to_time(video_preview.time as usize), video_preview.time));
+                    ui.separator();
+                    match found {
+                        Some((i, j)) => {
+                            // Scenes are kept in whole seconds, so the time of the frame is rounded.
+                            let time = video_preview.time.round() as usize;
+                            if ui.button("Set start here").on_hover_text(format!("Start the scene at {}", seconds_to_time(time))).clicked() {
+                                instructional.videos[i].scenes[j].start = time;
+                                sync_scene_start(&mut instructional.videos[i], j);
+                                refresh_needed = Some((i, j));
+                            }
+                            if ui.button("Set end here").on_hover_text(format!("End the scene at {}", seconds_to_time(time))).clicked() {
+                                instructional.videos[i].scenes[j].end = time;
+                                sync_scene_end(&mut instructional.videos[i], j);
+                                refresh_needed = Some((i, j));
+                            }
+                        },
+                        None => { ui.colored_label(Color32::YELLOW, "The scene was changed or removed"); },
+                    }
+                });
+            });
+            if let Some((i, j)) = refresh_needed {
+                // Keep following the scene with its new boundaries.
+                *scene = instructional.videos[i].scenes[j].clone();
+                sender.send(Command::AddPendingTasks{tasks: 1}).expect("Failed to send AddPendingTasks command!");
+                job_sender.send(Job::CreateThumbnail{ v_index: i, s_index: j, imageFn: create_scene_image}).expect("Failed to send CreateThumbnail command!");
+            }
+            if !open {
+                video_preview.close(frame);
+                *preview = None;
+            }
+        }
+
+        if let Some(status) = cache_status.as_ref() {
+            let mut open = true;
+            let mut refresh: Option<bool> = None;
+            let mut cleared = false;
+            egui::Window::new("Cache status").open(&mut open).show(ctx, |ui| {
+                egui::Grid::new("cache-status-grid").show(ui, |ui| {
+                    ui.label("Products:");
+                    ui.label(format!("{}", status.products));
+                    ui.end_row();
+                    ui.label("Updated:");
+                    match status.updated_at {
+                        Some(updated_at) => ui.label(format!("{} ago", format_age(now_in_secs().saturating_sub(updated_at)))),
+                        None => ui.label("never"),
+                    };
+                    ui.end_row();
+                    ui.label("Expired:");
+                    if status.expired {
+                        ui.colored_label(Color32::YELLOW, "yes");
+                    } else {
+                        ui.label("no");
+                    }
+                    ui.end_row();
+                    ui.label("Product pages:");
+                    ui.label(format!("{}", status.details));
+                    ui.end_row();
+                    ui.label("Size:");
+                    ui.label(format!("{:.1} MB", status.size as f64 / (1024.0 * 1024.0)));
+                    ui.end_row();
+                });
+                ui.horizontal(|ui| {
+                    if ui.button("Refresh").on_hover_text("Fetch products added since the last update").clicked() {
+                        refresh = Some(false);
+                    }
+                    if ui.button("Rebuild").on_hover_text("Fetch all products").clicked() {
+                        refresh = Some(true);
+                    }
+                    if ui.button("Clear product pages").clicked() {
+                        clear_product_details_cache();
+                        cleared = true;
+                    }
+                });
+            });
+            if let Some(full) = refresh {
+                sender.send(Command::AddPendingTasks{tasks: 1}).expect("Failed to send AddPendingTasks command!");
+                job_sender.send(Job::UpdateProductCache{full}).expect("Failed to send UpdateProductCache command!");
+            }
+            if !open {
+                *cache_status = None;
+            } else if cleared {
+                *cache_status = Some(get_cache_status());
+            }
+        }
+
+        if !errors.is_empty() {
+            let mut open = true;
+            let mut dismiss = false;
+            egui::Window::new("Errors").open(&mut open).show(ctx, |ui| {
+                errors.iter().for_each(|e| {
+                    ui.colored_label(Color32::RED, e.as_str());
+                });
+                if ui.button("Dismiss").clicked() {
+                    dismiss = true;
+                }
+            });
+            if dismiss || !open {
+                errors.clear();
+            }
+        }
+
+        if let Some((v_index, reconciled)) = reconciliation {
+            let mut open = true;
+            let mut apply = false;
+            egui::Window::new(format!("Reconcile volume {}", *v_index + 1)).open(&mut open).default_width(800.0).show(ctx, |ui| {
+                ui.label(format!("Matched: {}, unmatched detected: {}, unmatched scraped: {}",
+                                 reconciled.iter().filter(|r| r.is_matched()).count(),
+                                 reconciled.iter().filter(|r| r.scraped.is_none()).count(),
+                                 reconciled.iter().filter(|r| r.detected.is_none()).count()));
+                ui.horizontal(|ui| {
+                    if ui.button("Apply").clicked() {
+                        apply = true;
+                    }
+                });
+                ui.separator();
+                egui::ScrollArea::vertical()
+                    .max_height(600.0)
+                    .show(ui, |ui| {
+                        egui::Grid::new("reconcile-grid").striped(true).show(ui, |ui| {
+                            ui.label("Include");
+                            ui.label("Detected");
+                            ui.label("Scraped");
+                            ui.label("Title");
+                            ui.label("");
+                            ui.end_row();
+                            reconciled.iter_mut().for_each(|r| {
+                                ui.checkbox(&mut r.include, "");
+                                ui.label(r.detected.as_ref().map(|s| format!("{} {}", seconds_to_time(s.start), s.title)).unwrap_or("-".to_string()));
+                                ui.label(r.scraped.as_ref().map(|s| format!("{} {}", seconds_to_time(s.start), s.title)).unwrap_or("-".to_string()));
+                                ui.text_edit_singleline(&mut r.title);
+                                if r.is_matched() {
+                                    ui.label(format!("{:.0}%", r.score * 100.0));
+                                } else {
+                                    ui.colored_label(Color32::RED, "unmatched");
+                                }
+                                ui.end_row();
+                            });
+                        });
+                    });
+            });
+
+            if apply && instructional.videos.len() > *v_index {
+                let i = *v_index;
+                instructional.videos[i].scenes = apply_reconciliation(reconciled, &instructional.videos[i].file);
+                *scene_images = reallocate_scene_images(frame, &instructional.videos, i, scene_images.clone());
+                sender.send(Command::AddPendingTasks{tasks: instructional.videos[i].scenes.len()}).expect("Failed to send AddPendingTasks command!");
+                for j in 0..instructional.videos[i].scenes.len() {
+                    job_sender.send(Job::CreateThumbnail{ v_index: i, s_index: j, imageFn: create_scene_image}).expect("Failed to send CreateThumbnail command!");
+                }
+            }
+            if apply || !open {
+                *reconciliation = None;
+            }
+        }
+
         if false {
             egui::Window::new("Window").show(ctx, |ui| {
                 ui.label("Windows can be moved by dragging them.");
@@ -1345,6 +2635,19 @@ impl epi::App for App {
     }
 }
 
+/// Plays the current scene of the study session (from where it was interrupted), or stops when the session is finished.
+fn play_study_session(players: &mut PlayerRegistry, session: &StudySession, playing: &mut bool, player_running: &mut bool) {
+    match (session.current_scene(), session.resume_time()) {
+        (Some(scene), Some(start)) => {
+            let options = PlaybackOptions { mode: PlaybackMode::SceneOnly, speed: scene.speed, ..PlaybackOptions::new() };
+            players.main().open(&scene.file, start, scene.end, &options);
+            *playing = true;
+            *player_running = false;
+        },
+        _ => *playing = false,
+    }
+}
+
 fn add_video(videos: &mut Vec<Video>, last_selected: &mut String) {
     let dir = videos_dir(&videos.to_vec())
         .unwrap_or_else(|| or(last_selected.to_string(), "/".to_string()));
@@ -1374,6 +2677,20 @@ fn add_video(videos: &mut Vec<Video>, last_selected: &mut String) {
     };
 }
 
+/// Queues OCR for all the scenes of the specified video (or of all videos).
+/// Recognized titles are collected for review, instead of being applied directly.
+fn recognize_titles(sender: &mut Sender<Command>, job_sender: &mut Sender<Job>, instructional: &Instructional, v_index: Option<usize>) {
+    let scenes: Vec<(usize, usize)> = instructional.videos.iter()
+        .enumerate()
+        .filter(|(i, _)| v_index.map(|v| v == *i).unwrap_or(true))
+        .flat_map(|(i, v)| (0..v.scenes.len()).map(move |j| (i, j)))
+        .collect();
+    sender.send(Command::AddPendingTasks{tasks: scenes.len()}).expect("Failed to send AddPendingTasks command!");
+    scenes.into_iter().for_each(|(v_index, s_index)| {
+        job_sender.send(Job::RecognizeTitle{v_index, s_index}).expect("Failed to send RecognizeTitle command!");
+    });
+}
+
 fn sync_scene_start(video: &mut Video, j: usize) {
     if j >= 1 {
         video.scenes[j - 1].end = video.scenes[j].start;
@@ -1386,24 +2703,108 @@ fn sync_scene_end(video: &mut Video, j: usize) {
     }
 }
 
+fn format_age(secs: u64) -> String {
+    if secs < 60 * 60 {
+        format!("{} minute(s)", secs / 60)
+    } else if secs < 24 * 60 * 60 {
+        format!("{} hour(s)", secs / (60 * 60))
+    } else {
+        format!("{} day(s)", secs / (24 * 60 * 60))
+    }
+}
+
+/// Guesses the instructional from the video file names and prefills creator, title and url with the best match.
+/// Returns the confidence of the best match.
+fn identify_instructional(
+    instructional: &mut Instructional,
+    candidate_urls: &mut Vec<String>,
+    candidate_titles: &mut Vec<String>,
+    provider: &dyn MetadataProvider,
+    errors: &mut Vec<String>,
+) -> Option<f32> {
+    let files: Vec<String> = instructional.videos.iter().map(|v| v.file.to_string()).collect();
+    let guess = guess_from_files(&files)?;
+    println!("Identifying instructional. Creator: {:?}, title: {}", guess.creator, guess.title);
+    // Only the products that are already downloaded are searched, downloading them is up to the user (see `Job::RefreshProvider`).
+    let mut results = match provider.search_cached_products(guess.creator.clone().unwrap_or_default(), guess.title.to_string()) {
+        Ok(results) => results,
+        Err(e) => {
+            errors.push(e);
+            return None;
+        }
+    };
+    // The creator might be misspelled or missing from the file names, so search by title too.
+    if results.is_empty() && guess.creator.is_some() {
+        results = provider.search_cached_products("".to_string(), guess.title.to_string()).unwrap_or_default();
+    }
+    let best = results.iter()
+        .map(|r| (identification_confidence(&guess, r), r))
+        .max_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal))
+        .map(|(confidence, r)| (confidence, r.clone()));
+
+    match best {
+        Some((confidence, product)) => {
+            if confidence >= MIN_IDENTIFICATION_CONFIDENCE {
+                instructional.creator = product.creator.to_string();
+                instructional.title = product.title.to_string();
+                instructional.url = product.url.to_string();
+                *candidate_titles = results.iter().take(MAX_TITLE_CANDIDATES).map(|r| r.title.to_string()).collect();
+                *candidate_urls = results.iter().take(MAX_TITLE_CANDIDATES).map(|r| r.url.to_string()).collect();
+            } else {
+                instructional.creator = guess.creator.unwrap_or_default();
+                instructional.title = guess.title;
+            }
+            Some(confidence)
+        },
+        None => {
+            instructional.creator = guess.creator.unwrap_or_default();
+            instructional.title = guess.title;
+            Some(0.0)
+        }
+    }
+}
+
+/// Sorts the videos (and their scene images) by the volume number found in the file names.
+fn order_videos_by_volume(instructional: &mut Instructional, scene_images: &mut Vec<Vec<Option<TextureId>>>) {
+    let files: Vec<String> = instructional.videos.iter().map(|v| v.file.to_string()).collect();
+    let order = volume_order(&files);
+    instructional.videos = order.iter().map(|i| instructional.videos[*i].clone()).collect();
+    instructional.videos.iter_mut().enumerate().for_each(|(i, v)| v.index = i);
+    if scene_images.len() == files.len() {
+        *scene_images = order.iter().map(|i| scene_images[*i].clone()).collect();
+    }
+}
+
+/// Fills the title combo with the best matches for the creator and title query.
 fn refresh_titles(
     instructional: &mut Instructional,
+    query: String,
     candidate_urls: &mut Vec<String>,
     candidate_titles: &mut Vec<String>,
+    sender: &Sender<Command>,
+    job_sender: &Sender<Job>,
 ) {
-    let instructionals: Vec<Instructional> =
-        search_product(instructional.creator.to_string(), "".to_string());
     instructional.title = "".to_string();
     *candidate_urls = vec![];
     *candidate_titles = vec![];
-    instructionals
-        .iter()
-        .map(|i| i.url.to_string())
-        .for_each(|u| candidate_urls.push(u));
-    instructionals
-        .into_iter()
-        .map(|i| i.title)
-        .for_each(|t| candidate_titles.push(t));
+    // The candidates are filled in when the search completes.
+    sender.send(Command::AddPendingTasks{tasks: 1}).expect("Failed to send AddPendingTasks command!");
+    job_sender.send(Job::SearchTitles{creator: instructional.creator.to_string(), query}).expect("Failed to send SearchTitles command!");
+}
+
+/// Recreates the provider (and its popular creators) when the provider id or the HTTP settings change, so that the provider (and its rate limit) is otherwise shared.
+fn update_provider(
+    provider: &mut Arc<dyn MetadataProvider>,
+    provider_key: &mut (String, HttpSettings),
+    popular_creators: &mut Vec<String>,
+    id: &str,
+    http_settings: &HttpSettings,
+) {
+    if provider_key.0 != id || provider_key.1 != *http_settings {
+        *provider = get_provider(id, http_settings);
+        *provider_key = (id.to_string(), http_settings.clone());
+        *popular_creators = provider.popular_creators();
+    }
 }
 
 fn create_ocr_image(
@@ -1417,6 +2818,21 @@ fn create_ocr_image(
     load_texture_id(&frame, Path::new(&ocr_filename))
 }
 
+fn create_ocr_preview_images(
+    frame: &epi::Frame,
+    creator: String,
+    title: String,
+    s: &Scene,
+    ocr_settings: &OcrSettings,
+) -> Vec<(String, egui::TextureId)> {
+    scene_to_image(creator, title, s)
+        .map(|img| ocr_preview_img(img, ocr_settings))
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|(name, path)| load_texture_id(&frame, Path::new(&path)).map(|t| (name, t)))
+        .collect()
+}
+
 fn create_scene_image(
     frame: &epi::Frame,
     creator: String,
@@ -1485,6 +2901,14 @@ fn load_texture_id(frame: &epi::Frame, path: &Path) -> Option<egui::TextureId> {
     Some(frame.alloc_texture(load_image(path)?))
 }
 
+/// A small button with the icon, or with its name if the icon is not loaded.
+fn icon_button(ui: &mut Ui, icons: &HashMap<&'static str, TextureId>, name: &str) -> Response {
+    match icons.get(name) {
+        Some(icon) => ui.add(egui::ImageButton::new(*icon, (10.0, 10.0))),
+        None => ui.small_button(name),
+    }
+}
+
 pub fn drag_source(ui: &mut egui::Ui, id: Id, body: impl FnOnce(&mut Ui)) {
     let is_being_dragged = ui.memory().is_being_dragged(id);
 
@@ -1672,8 +3096,19 @@ fn save_org_in_dir (instructional: &mut Instructional, index_file_name: String)
     save_org(instructional, File::create(target_file.into_os_string()).expect("Failed to create file."), false);
 }
 
-fn save_md_in_dir (instructional: &mut Instructional, index_file_name: String) {
+fn save_md_in_dir (instructional: &mut Instructional, index_file_name: String, errors: &mut Vec<String>) {
     let video = instructional.videos.first().expect("Failed to find videos in instructional!");
-    let target_file = PathBuf::from(&video.file).parent().expect("Failed to find part of file!").join(index_file_name);
-    save_md(instructional, File::create(target_file.into_os_string()).expect("Failed to create file."));
+    let target_dir = PathBuf::from(&video.file).parent().expect("Failed to find part of file!").to_path_buf();
+    // The markdown refers to the cover next to it.
+    let target_cover = target_dir.join(cover_file_name(&instructional.cover));
+    if !instructional.cover.is_empty() {
+        let cover = cover_img_path(&instructional.cover);
+        if cover.exists() {
+            if let Err(e) = std::fs::copy(cover, &target_cover) {
+                errors.push(format!("Failed to copy the cover to: {}. Error: {}", target_cover.display(), e));
+            }
+        }
+    }
+    let with_cover = !instructional.cover.is_empty() && target_cover.exists();
+    save_md(instructional, File::create(target_dir.join(index_file_name).into_os_string()).expect("Failed to create file."), with_cover);
 }
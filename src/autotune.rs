@@ -0,0 +1,187 @@
+use crate::GeneralSettings;
+use crate::Instructional;
+use crate::OcrSettings;
+use crate::OcrStep;
+use crate::Scene;
+use crate::extract_timestamps;
+use crate::scene_text_with_settings;
+use crate::similarity;
+
+/// The maximum distance (in seconds) between a detected and a scraped scene start, for the two to be considered the same scene.
+static MAX_START_DISTANCE: usize = 10;
+
+/// The combinations of preprocessing settings that auto-tuning evaluates.
+/// Settings that are not part of the search (e.g. region of interest, language and steps like resize or color key) are kept from the base settings.
+pub fn autotune_candidates(base: &OcrSettings) -> Vec<OcrSettings> {
+    // Steps that are not searched over, are kept in front of the searched ones.
+    let fixed: Vec<OcrStep> = base.steps.iter()
+        .filter(|s| !is_tuned_step(s))
+        .cloned()
+        .collect();
+
+    let thresholds: Vec<Option<OcrStep>> = vec![
+        None,
+        Some(OcrStep::GaussianThresholding { max_value: 255.0, blocksize: 11, c: 2.0 }),
+        Some(OcrStep::GaussianThresholding { max_value: 255.0, blocksize: 11, c: 10.0 }),
+        Some(OcrStep::GaussianThresholding { max_value: 255.0, blocksize: 31, c: 2.0 }),
+        Some(OcrStep::GaussianThresholding { max_value: 255.0, blocksize: 31, c: 10.0 }),
+        Some(OcrStep::OtsuThresholding { min_value: 0.0, max_value: 255.0 }),
+    ];
+    let denoise: Vec<Option<OcrStep>> = vec![
+        None,
+        Some(OcrStep::Denoise { strength: 3.0 }),
+        Some(OcrStep::Denoise { strength: 10.0 }),
+    ];
+    let morphology: Vec<Option<i32>> = vec![None, Some(3)];
+    let invert: Vec<bool> = vec![true, false];
+
+    let mut result: Vec<OcrSettings> = vec![];
+    for threshold in &thresholds {
+        for denoise_step in &denoise {
+            for kernel_size in &morphology {
+                for inv in &invert {
+                    let mut steps = fixed.clone();
+                    steps.push(OcrStep::Grayscale);
+                    steps.extend(threshold.clone());
+                    if *inv {
+                        steps.push(OcrStep::Invert);
+                    }
+                    steps.extend(denoise_step.clone());
+                    if let Some(kernel_size) = kernel_size {
+                        steps.push(OcrStep::Erode { kernel_size: *kernel_size, iterations: 1 });
+                        steps.push(OcrStep::Dilate { kernel_size: *kernel_size, iterations: 1 });
+                    }
+                    let mut settings = base.clone();
+                    settings.steps = steps;
+                    // Spellchecking is slow and hides how good the preprocessing really is.
+                    settings.spellcheking = false;
+                    result.push(settings);
+                }
+            }
+        }
+    }
+    result
+}
+
+fn is_tuned_step(step: &OcrStep) -> bool {
+    match step {
+        OcrStep::Grayscale
+            | OcrStep::GaussianThresholding { .. }
+            | OcrStep::OtsuThresholding { .. }
+            | OcrStep::Invert
+            | OcrStep::Denoise { .. }
+            | OcrStep::Erode { .. }
+            | OcrStep::Dilate { .. } => true,
+        _ => false,
+    }
+}
+
+/// Pairs the scenes of the instructional with the scraped titles (ground truth) and picks up to `max_samples` evenly spaced pairs.
+pub fn autotune_samples(instructional: &Instructional, max_samples: usize) -> Vec<(Scene, String)> {
+    let scraped = extract_timestamps(instructional.timestamps.clone());
+    let mut pairs: Vec<(Scene, String)> = vec![];
+    for (v, video) in instructional.videos.iter().enumerate() {
+        if v >= scraped.len() {
+            break;
+        }
+        for scene in video.scenes.iter().filter(|s| !s.file.is_empty()) {
+            let closest = scraped[v].iter()
+                .min_by_key(|k| abs_diff(k.start, scene.start));
+            if let Some(known) = closest {
+                if abs_diff(known.start, scene.start) <= MAX_START_DISTANCE {
+                    pairs.push((scene.clone(), known.title.to_string()));
+                }
+            }
+        }
+    }
+
+    if max_samples == 0 || pairs.len() <= max_samples {
+        return pairs;
+    }
+    let step = pairs.len() as f32 / max_samples as f32;
+    (0..max_samples)
+        .map(|i| pairs[(i as f32 * step) as usize].clone())
+        .collect()
+}
+
+/// The average similarity of the recognized titles to the expected ones.
+pub fn autotune_score(recognized: &Vec<String>, expected: &Vec<String>) -> f32 {
+    if expected.is_empty() {
+        return 0.0;
+    }
+    recognized.iter()
+        .zip(expected.iter())
+        .map(|(r, e)| similarity(r, e))
+        .sum::<f32>() / expected.len() as f32
+}
+
+/// Runs OCR on the samples with every candidate setting and returns the best scoring settings along with their score.
+pub fn autotune_ocr(creator: String, title: String, samples: &Vec<(Scene, String)>, base: &OcrSettings, general_settings: &GeneralSettings) -> Option<(OcrSettings, f32)> {
+    if samples.is_empty() {
+        println!("No scenes with known titles found. Skipping auto-tuning.");
+        return None;
+    }
+    let expected: Vec<String> = samples.iter().map(|(_, t)| t.to_string()).collect();
+    let candidates = autotune_candidates(base);
+    let mut best: Option<(OcrSettings, f32)> = None;
+    for (i, candidate) in candidates.iter().enumerate() {
+        let recognized: Vec<String> = samples.iter()
+            .map(|(scene, _)| scene_text_with_settings(creator.to_string(), title.to_string(), scene, general_settings, candidate).unwrap_or_default())
+            .collect();
+        let score = autotune_score(&recognized, &expected);
+        println!("Auto-tuning candidate {} of {}: score: {:.3}", i + 1, candidates.len(), score);
+        if best.as_ref().map(|(_, s)| score > *s).unwrap_or(true) {
+            best = Some((candidate.clone(), score));
+        }
+    }
+    best.map(|(mut settings, score)| {
+        settings.spellcheking = base.spellcheking;
+        (settings, score)
+    })
+}
+
+fn abs_diff(a: usize, b: usize) -> usize {
+    if a > b { a - b } else { b - a }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Video;
+
+    #[test]
+    fn test_autotune_score() {
+        let expected = vec!["Rear Naked Choke".to_string(), "Arm Bar".to_string()];
+        assert_eq!(1.0, autotune_score(&vec!["rear naked choke".to_string(), "arm bar".to_string()], &expected));
+        assert!(autotune_score(&vec!["Rear Nakcd Choke".to_string(), "".to_string()], &expected) < 0.5);
+        assert_eq!(0.0, autotune_score(&vec![], &vec![]));
+    }
+
+    #[test]
+    fn test_autotune_candidates_keep_fixed_steps() {
+        let mut base = OcrSettings::new();
+        base.steps = vec![OcrStep::Resize { scale: 2.0 }, OcrStep::Grayscale, OcrStep::Invert];
+        let candidates = autotune_candidates(&base);
+        assert_eq!(6 * 3 * 2 * 2, candidates.len());
+        assert!(candidates.iter().all(|c| c.steps[0] == OcrStep::Resize { scale: 2.0 }));
+        assert!(candidates.iter().all(|c| c.steps.iter().filter(|s| **s == OcrStep::Resize { scale: 2.0 }).count() == 1));
+    }
+
+    #[test]
+    fn test_autotune_samples() {
+        let scene = |index: usize, start: usize, end: usize| Scene { index, title: format!("Scene {}", index), file: "vol1.mp4".to_string(), text: "".to_string(), labels: vec![], start, end, speed: 1.0 };
+        let instructional = Instructional {
+            creator: "iocanel".to_string(),
+            title: "my test".to_string(),
+            url: "".to_string(),
+            timestamps: "Intro\n00:00:00 - 00:01:00\nArm Bar\n00:01:00 - 00:05:00\nTriangle\n00:05:00 - 00:10:00".to_string(),
+            videos: vec![Video { index: 0, file: "vol1.mp4".to_string(), duration: 600, scenes: vec![scene(0, 1, 62), scene(1, 62, 200), scene(2, 400, 600)] }],
+            ..Instructional::new()
+        };
+        let samples = autotune_samples(&instructional, 5);
+        assert_eq!(2, samples.len());
+        assert_eq!("Intro", samples[0].1);
+        assert_eq!("Arm Bar", samples[1].1);
+        assert_eq!(1, autotune_samples(&instructional, 1).len());
+    }
+}
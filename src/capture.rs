@@ -0,0 +1,126 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+use crate::Instructional;
+use crate::PlayerAction;
+use crate::find_playing_scene;
+use crate::get_data_dir;
+use crate::seconds_to_time;
+
+/// The target of the script messages sent by the key bindings.
+pub static SCRIPT_MESSAGE_TARGET: &str = "hg2jj";
+
+/// The key bindings that send the playback time back to the app (see `parse_script_message`).
+/// The note binding opens the mpv console, so that the note can be typed after the command.
+static MPV_KEY_BINDINGS: &str = "
+# hg2jj: capture bookmarks, scene boundaries and notes while watching.
+Ctrl+b script-message hg2jj bookmark
+Ctrl+a script-message hg2jj scene-start
+Ctrl+e script-message hg2jj scene-end
+Ctrl+n script-message-to console type \"script-message hg2jj note \"
+";
+
+/// The action requested by a script message (e.g. `script-message hg2jj bookmark`). Messages for other targets are ignored.
+pub fn parse_script_message(args: &[String]) -> Option<PlayerAction> {
+    if args.first().map(|a| a.as_str()) != Some(SCRIPT_MESSAGE_TARGET) {
+        return None;
+    }
+    match args.get(1).map(|a| a.as_str()) {
+        Some("bookmark") => Some(PlayerAction::Bookmark),
+        Some("scene-start") => Some(PlayerAction::SceneStart),
+        Some("scene-end") => Some(PlayerAction::SceneEnd),
+        Some("note") => {
+            // The console splits the note into words.
+            let note = args[2..].join(" ").trim().to_string();
+            if note.is_empty() { None } else { Some(PlayerAction::Note(note)) }
+        },
+        _ => None,
+    }
+}
+
+/// The input.conf of the user, so that their bindings are kept when ours are added.
+fn get_user_mpv_input_conf() -> Option<PathBuf> {
+    let dir = match std::env::var("MPV_HOME") {
+        Ok(d) => PathBuf::from(d),
+        Err(_) if cfg!(target_os = "windows") => PathBuf::from(std::env::var("APPDATA").ok()?).join("mpv"),
+        Err(_) => PathBuf::from(std::env::var("HOME").ok()?).join(".config").join("mpv"),
+    };
+    Some(dir.join("input.conf")).filter(|p| p.exists())
+}
+
+pub fn get_mpv_input_conf_file() -> PathBuf {
+    get_data_dir().join("mpv-input.conf")
+}
+
+/// Writes the input.conf passed to mpv: the bindings of the user followed by ours. Returns None if it can't be written.
+pub fn write_mpv_input_conf() -> Option<PathBuf> {
+    let path = get_mpv_input_conf_file();
+    std::fs::create_dir_all(path.parent()?).ok()?;
+    let user_conf = get_user_mpv_input_conf().and_then(|p| std::fs::read_to_string(p).ok()).unwrap_or_default();
+    let file = File::create(&path).ok()?;
+    let mut out = BufWriter::new(file);
+    out.write_all(format!("{}\n{}", user_conf, MPV_KEY_BINDINGS).as_bytes()).ok()?;
+    Some(path)
+}
+
+/// Applies an action captured from inside the player to the scene playing at the time. Returns the scene that was changed.
+/// Bookmarks and notes are added as timestamped lines to the text of the scene, scene start / end move the boundaries of the scene.
+pub fn apply_player_action(instructional: &mut Instructional, path: &str, playback_time: f64, action: &PlayerAction) -> Option<(usize, usize)> {
+    let (i, j) = find_playing_scene(instructional, path, playback_time)?;
+    let time = playback_time as usize;
+    let video = &mut instructional.videos[i];
+    match action {
+        PlayerAction::Bookmark => append_line(&mut video.scenes[j].text, format!("- {} Bookmark", seconds_to_time(time))),
+        PlayerAction::Note(note) => append_line(&mut video.scenes[j].text, format!("- {} {}", seconds_to_time(time), note)),
+        PlayerAction::SceneStart => {
+            video.scenes[j].start = time;
+            if j >= 1 {
+                video.scenes[j - 1].end = time;
+            }
+        },
+        PlayerAction::SceneEnd => {
+            video.scenes[j].end = time;
+            if j + 1 < video.scenes.len() {
+                video.scenes[j + 1].start = time;
+            }
+        },
+    }
+    Some((i, j))
+}
+
+fn append_line(text: &mut String, line: String) {
+    if !text.trim().is_empty() {
+        text.push('\n');
+    }
+    text.push_str(&line);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Scene;
+    use crate::Video;
+
+    #[test]
+    fn test_apply_player_action() {
+        let args = |a: &str| a.split(' ').map(|s| s.to_string()).collect::<Vec<String>>();
+        assert_eq!(Some(PlayerAction::Note("Keep the elbow tight".to_string())), parse_script_message(&args("hg2jj note Keep the elbow tight")));
+        assert_eq!(Some(PlayerAction::Bookmark), parse_script_message(&args("hg2jj bookmark")));
+        assert_eq!(None, parse_script_message(&args("hg2jj note")));
+        assert_eq!(None, parse_script_message(&args("other bookmark")));
+
+        let mut instructional = Instructional::new();
+        let scene = |start: usize, end: usize| Scene { index: 0, title: "".to_string(), file: "/videos/vol1.mp4".to_string(), text: "".to_string(), labels: vec![], start, end, speed: 1.0 };
+        instructional.videos.push(Video { index: 0, file: "/videos/vol1.mp4".to_string(), duration: 600, scenes: vec![scene(0, 60), scene(60, 120)] });
+
+        assert_eq!(Some((0, 1)), apply_player_action(&mut instructional, "/videos/vol1.mp4", 75.5, &PlayerAction::Bookmark));
+        apply_player_action(&mut instructional, "/videos/vol1.mp4", 80.0, &PlayerAction::Note("Grips".to_string()));
+        assert_eq!("- 00:01:15 Bookmark\n- 00:01:20 Grips", instructional.videos[0].scenes[1].text);
+
+        apply_player_action(&mut instructional, "/videos/vol1.mp4", 65.0, &PlayerAction::SceneStart);
+        assert_eq!((0, 65, 65), (instructional.videos[0].scenes[0].start, instructional.videos[0].scenes[0].end, instructional.videos[0].scenes[1].start));
+        apply_player_action(&mut instructional, "/videos/vol1.mp4", 30.0, &PlayerAction::SceneEnd);
+        assert_eq!((30, 30), (instructional.videos[0].scenes[0].end, instructional.videos[0].scenes[1].start));
+        assert_eq!(None, apply_player_action(&mut instructional, "/videos/vol2.mp4", 30.0, &PlayerAction::Bookmark));
+    }
+}
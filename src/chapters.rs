@@ -0,0 +1,84 @@
+use std::fs;
+use std::path::Path;
+use serde_json::Value;
+use crate::seconds_to_time;
+
+/// Cleans up pasted text (e.g. a course or video description) so that it can be parsed as timestamps.
+pub fn chapters_from_text(text: &str) -> String {
+    text.replace("\r\n", "\n")
+        .replace('\r', "\n")
+        .lines()
+        .map(|l| l.trim_end())
+        .collect::<Vec<&str>>()
+        .join("\n")
+        .trim()
+        .to_string()
+}
+
+/// Converts the `chapters` of a `yt-dlp` `.info.json` file into timestamps (e.g. `00:00:00 - 00:05:30 Intro`).
+/// Videos without chapters fall back to their description, which usually contains the chapter list.
+pub fn chapters_from_info_json(json: &str) -> Result<String, String> {
+    let info: Value = serde_json::from_str(json).map_err(|e| format!("Failed to parse info json: {}", e))?;
+    let chapters = info["chapters"].as_array().cloned().unwrap_or_default();
+    if !chapters.is_empty() {
+        return Ok(chapters.iter()
+            .filter_map(|c| {
+                let start = c["start_time"].as_f64()? as usize;
+                let title = c["title"].as_str().unwrap_or_default().trim();
+                Some(match c["end_time"].as_f64() {
+                    Some(end) => format!("{} - {} {}", seconds_to_time(start), seconds_to_time(end as usize), title),
+                    None => format!("{} {}", seconds_to_time(start), title),
+                })
+            })
+            .collect::<Vec<String>>()
+            .join("\n"));
+    }
+    match info["description"].as_str() {
+        Some(description) if !description.trim().is_empty() => Ok(chapters_from_text(description)),
+        _ => Err("The info json contains neither chapters nor a description.".to_string()),
+    }
+}
+
+/// Reads the chapters of a `.txt` or a `yt-dlp` `.info.json` file.
+pub fn chapters_from_file(path: &Path) -> Result<String, String> {
+    let content = fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    match path.extension().and_then(|e| e.to_str()) {
+        Some("json") => chapters_from_info_json(&content),
+        _ => Ok(chapters_from_text(&content)),
+    }
+}
+
+/// Reads the chapters of multiple files (one per volume), separating them with volume headers.
+pub fn chapters_from_files(paths: &Vec<std::path::PathBuf>) -> Result<String, String> {
+    if paths.len() == 1 {
+        return chapters_from_file(&paths[0]);
+    }
+    paths.iter()
+        .enumerate()
+        .map(|(i, p)| chapters_from_file(p).map(|chapters| format!("Volume {}\n{}", i + 1, chapters)))
+        .collect::<Result<Vec<String>, String>>()
+        .map(|volumes| volumes.join("\n\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_timestamps;
+
+    #[test]
+    fn test_chapters_from_info_json() {
+        let json = r#"{"title": "Half Guard Seminar", "description": "ignored", "chapters": [
+            {"start_time": 0.0, "end_time": 95.5, "title": "Intro"},
+            {"start_time": 95.5, "end_time": 600.0, "title": "Knee Shield"}]}"#;
+        let timestamps = chapters_from_info_json(json).unwrap();
+        assert_eq!("00:00:00 - 00:01:35 Intro\n00:01:35 - 00:10:00 Knee Shield", timestamps);
+        let parsed = parse_timestamps(&timestamps);
+        assert_eq!(1, parsed.volumes.len());
+        assert_eq!(2, parsed.volumes[0].len());
+        assert!(parsed.diagnostics.is_empty());
+
+        let json = r#"{"title": "Half Guard Seminar", "description": "Intro 0:00\r\nKnee Shield 1:35\r\n", "chapters": null}"#;
+        assert_eq!("Intro 0:00\nKnee Shield 1:35", chapters_from_info_json(json).unwrap());
+        assert!(chapters_from_info_json(r#"{"title": "Half Guard Seminar"}"#).is_err());
+    }
+}
@@ -0,0 +1,88 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use itertools::Itertools;
+use crate::Instructional;
+use crate::Scene;
+use crate::all_scenes;
+use crate::clean_title;
+
+static EDL_HEADER: &str = "# mpv EDL v0";
+
+/// A single EDL segment: the file (length-prefixed, so that commas and other special characters need no escaping), the start and the length.
+/// Scenes without an end play until the end of the file.
+pub fn edl_segment(file: &str, start: usize, end: usize) -> String {
+    let file = format!("%{}%{}", file.len(), file);
+    if end > start {
+        format!("{},{},{}", file, start, end - start)
+    } else {
+        format!("{},{}", file, start)
+    }
+}
+
+/// An EDL that plays the scenes one after the other, as if they were a single clip.
+pub fn scenes_to_edl(scenes: &Vec<Scene>) -> String {
+    let mut lines = vec![EDL_HEADER.to_string()];
+    scenes.iter().for_each(|s| lines.push(edl_segment(&s.file, s.start, s.end)));
+    lines.join("\n") + "\n"
+}
+
+/// The scenes grouped by label (a scene with multiple labels appears in all of them).
+pub fn scenes_by_label(scenes: &Vec<Scene>) -> Vec<(String, Vec<Scene>)> {
+    scenes.iter()
+        .flat_map(|s| s.labels.iter().map(move |l| (l.trim().to_string(), s.clone())))
+        .filter(|(l, _)| !l.is_empty())
+        .sorted_by(|(a, _), (b, _)| a.cmp(b))
+        .group_by(|(l, _)| l.to_string())
+        .into_iter()
+        .map(|(l, group)| (l, group.map(|(_, s)| s).collect()))
+        .collect()
+}
+
+fn write_edl(path: &Path, scenes: &Vec<Scene>) -> PathBuf {
+    let file = File::create(path).expect("Failed to create EDL file!");
+    let mut out = BufWriter::new(file);
+    out.write_all(scenes_to_edl(scenes).as_bytes()).expect("Failed to write EDL file!");
+    path.to_path_buf()
+}
+
+/// Writes one EDL per scene (named like the scenes created by splitting), one per label and one for the whole instructional.
+/// The EDL files refer to the original volumes, so nothing is re-encoded. Returns the files written.
+pub fn export_edl(instructional: &Instructional, dir: &Path) -> Vec<PathBuf> {
+    std::fs::create_dir_all(dir).expect("Failed to create EDL directory!");
+    let scenes = all_scenes(instructional.clone());
+    let mut written: Vec<PathBuf> = scenes.iter().enumerate()
+        .map(|(i, s)| write_edl(&dir.join(format!("{:03}. {}.edl", i + 1, clean_title(s.title.to_string()))), &vec![s.clone()]))
+        .collect();
+    scenes_by_label(&scenes).iter().for_each(|(label, labeled)| {
+        written.push(write_edl(&dir.join(format!("Label - {}.edl", clean_title(label.to_string()))), labeled));
+    });
+    if !scenes.is_empty() {
+        let name = if instructional.title.is_empty() { "playlist".to_string() } else { clean_title(instructional.title.to_string()) };
+        written.push(write_edl(&dir.join(format!("{}.edl", name)), &scenes));
+    }
+    written
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scene(file: &str, title: &str, start: usize, end: usize, labels: Vec<&str>) -> Scene {
+        Scene { index: 0, title: title.to_string(), file: file.to_string(), text: "".to_string(), labels: labels.iter().map(|l| l.to_string()).collect(), start, end, speed: 1.0 }
+    }
+
+    #[test]
+    fn test_scenes_to_edl() {
+        let scenes = vec![scene("/videos/Vol 1, Basics.mp4", "Intro", 0, 60, vec![]), scene("/videos/Vol 2.mp4", "Outro", 300, 0, vec![])];
+        assert_eq!("# mpv EDL v0\n%25%/videos/Vol 1, Basics.mp4,0,60\n%17%/videos/Vol 2.mp4,300\n", scenes_to_edl(&scenes));
+    }
+
+    #[test]
+    fn test_scenes_by_label() {
+        let scenes = vec![scene("v1.mp4", "Arm Bar", 0, 60, vec!["submission", "favorite"]), scene("v2.mp4", "Kimura", 60, 120, vec!["submission"]), scene("v2.mp4", "Intro", 0, 60, vec![])];
+        let labeled = scenes_by_label(&scenes);
+        assert_eq!(vec!["favorite", "submission"], labeled.iter().map(|(l, _)| l.as_str()).collect::<Vec<&str>>());
+        assert_eq!(vec!["Arm Bar", "Kimura"], labeled[1].1.iter().map(|s| s.title.as_str()).collect::<Vec<&str>>());
+    }
+}
@@ -20,19 +20,55 @@ use crate::Instructional;
 use crate::time_to_seconds;
 use crate::get_cache_dir;
 use crate::clean_title;
+use crate::MetadataProvider;
+use crate::HttpClient;
+use crate::HttpError;
+use crate::HttpSettings;
+use crate::rank_by_match;
 
 
+/// How long (in seconds) the product list is used before it's refreshed.
+static PRODUCTS_TTL: u64 = 7 * 24 * 60 * 60;
+/// How long (in seconds) a cached product page is used before it's downloaded again.
+static PRODUCT_DETAILS_TTL: u64 = 30 * 24 * 60 * 60;
+
 #[derive(serde::Serialize, serde::Deserialize, Clone)]
 pub struct Products {
     pub products: Vec<Product>,
+    // When the list was last refreshed (seconds since the epoch). Zero for caches created before it was recorded.
+    #[serde(default)]
+    pub updated_at: u64,
 }
 
-#[derive(serde::Serialize, serde::Deserialize, Clone)]
+/// A summary of what's in the cache.
+#[derive(Debug, Clone)]
+pub struct CacheStatus {
+    pub products: usize,
+    pub updated_at: Option<u64>,
+    pub expired: bool,
+    pub details: usize,
+    pub size: u64,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, Default)]
 pub struct Product {
     pub id: usize,
     pub vendor: String,
     pub title: String,
-    pub handle: String
+    pub handle: String,
+    #[serde(default)]
+    pub body_html: Option<String>,
+    #[serde(default)]
+    pub published_at: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub images: Vec<ProductImage>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, Default)]
+pub struct ProductImage {
+    pub src: String,
 }
 
 pub fn get_popular_creators() -> Vec<String> {
@@ -42,48 +78,125 @@ pub fn get_popular_creators() -> Vec<String> {
 static BLANK: &str = "";
 
 pub fn get_cached_creators() -> Vec<String> {
+    match load_products_cache() {
+        Some(data) => data.products.into_iter()
+            .map(|p| p.vendor)
+            .sorted()
+            .dedup()
+            .collect(),
+        None => get_popular_creators(),
+    }
+}
+
+fn get_fanatics_cache_dir() -> PathBuf {
     let fanatics_dir = get_cache_dir().join("bjj-fanatics");
-    let path = fanatics_dir.join("products.json");
-    std::fs::create_dir_all(&fanatics_dir).unwrap();
-    // Try to fetch data from cache.
-    if path.exists() {
-        let file = File::open(path).unwrap();
-        let reader = BufReader::new(file);
-        let data: Products = serde_json::from_reader(reader).unwrap();
-        return data.products.into_iter()
-        .map(|p| p.vendor)
-        .sorted()
-        .dedup()
-        .collect();
+    std::fs::create_dir_all(&fanatics_dir).expect("Failed to create cache dir!");
+    fanatics_dir
+}
+
+fn get_products_cache_file() -> PathBuf {
+    get_fanatics_cache_dir().join("products.json")
+}
+
+pub fn load_products_cache() -> Option<Products> {
+    let path = get_products_cache_file();
+    if !path.exists() {
+        return None;
+    }
+    let file = File::open(&path).expect("Failed to open products cache!");
+    let reader = BufReader::new(file);
+    match serde_json::from_reader(reader) {
+        Ok(data) => Some(data),
+        Err(e) => {
+            println!("Failed to read products cache: {}. Error: {}", path.display(), e);
+            None
+        }
+    }
+}
+
+pub fn save_products_cache(products: &Products) {
+    let file = File::create(get_products_cache_file()).expect("Failed to create products cache!");
+    let mut out = BufWriter::new(file);
+    out.write_all(serde_json::to_string(products).expect("Failed to serialize products!").as_bytes()).expect("Failed to write products to file!");
+}
+
+pub fn now_in_secs() -> u64 {
+    std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default()
+}
+
+pub fn is_products_cache_expired(products: &Products, now: u64) -> bool {
+    now.saturating_sub(products.updated_at) > PRODUCTS_TTL
+}
+
+pub fn get_cache_status() -> CacheStatus {
+    let cached = load_products_cache();
+    let mut details = 0;
+    let mut size = 0;
+    if let Ok(entries) = std::fs::read_dir(get_fanatics_cache_dir()) {
+        entries.filter_map(|e| e.ok())
+            .filter_map(|e| e.metadata().ok().map(|m| (e.file_name(), m)))
+            .filter(|(_, m)| m.is_file())
+            .for_each(|(name, m)| {
+                size += m.len();
+                if name != "products.json" {
+                    details += 1;
+                }
+            });
+    }
+    CacheStatus {
+        products: cached.as_ref().map(|p| p.products.len()).unwrap_or_default(),
+        updated_at: cached.as_ref().map(|p| p.updated_at).filter(|u| *u > 0),
+        expired: cached.as_ref().map(|p| is_products_cache_expired(p, now_in_secs())).unwrap_or(true),
+        details,
+        size,
     }
-    return get_popular_creators();
 }
 
-pub fn scrape_url(url: String) -> String {
+/// Removes the cached product pages. The product list is kept.
+pub fn clear_product_details_cache() {
+    if let Ok(entries) = std::fs::read_dir(get_fanatics_cache_dir()) {
+        entries.filter_map(|e| e.ok())
+            .filter(|e| e.file_name() != "products.json" && e.path().is_file())
+            .for_each(|e| std::fs::remove_file(e.path()).expect("Failed to remove cached product page!"));
+    }
+}
+
+pub fn scrape_url(client: &HttpClient, url: String) -> Result<String, HttpError> {
    let id = url.split("/").last().unwrap();
-   let fanatics_dir = get_cache_dir().join("bjj-fanatics");
-   let path = fanatics_dir.join(id);
-   std::fs::create_dir_all(fanatics_dir).unwrap();
-
-    if path.exists() {
-        let mut f = File::open(path).expect("Failed to open timstamps file from cache!");
-        let mut content = String::new();
-        f.read_to_string(&mut content).expect("Failed to read file!");
-        return scrape_html(content);
+   let path = get_fanatics_cache_dir().join(id);
+
+    let age = std::fs::metadata(&path).ok()
+        .and_then(|m| m.modified().ok())
+        .and_then(|m| m.elapsed().ok())
+        .map(|d| d.as_secs());
+
+    if let Some(age) = age {
+        if age <= PRODUCT_DETAILS_TTL {
+            return Ok(scrape_html(read_cached_page(&path)));
+        }
     }
 
-   let client = reqwest::blocking::Client::builder().cookie_store(false).build().ok().unwrap();
-    let response = client.get(url)
-        .header("user-agent", "rust")
-        .header("accept", "*/*")
-        .send()
-        .ok()
-        .map(|r| r.text().ok().unwrap_or_default())
-        .unwrap_or_default();
+    match client.get(&url) {
+        Ok(response) => {
+            // Only successful responses are cached, so that failures can be retried.
+            let mut f = File::create(path).expect("Failed to open timstamps file from cache!");
+            f.write_all(&response.as_bytes()).expect("Failed to write timepstamps to cache!");
+            Ok(scrape_html(response))
+        },
+        // Use the expired page, when offline.
+        Err(e) if age.is_some() => {
+            println!("{}. Using cached page.", e);
+            Ok(scrape_html(read_cached_page(&path)))
+        },
+        Err(e) => Err(e),
+    }
+}
 
-    let mut f = File::create(path).expect("Failed to open timstamps file from cache!");
-    f.write_all(&response.as_bytes()).expect("Failed to write timepstamps to cache!");
-    scrape_html(response)
+fn read_cached_page(path: &PathBuf) -> String {
+    let mut f = File::open(path).expect("Failed to open timstamps file from cache!");
+    let mut content = String::new();
+    f.read_to_string(&mut content).expect("Failed to read file!");
+    content
 }
 
 pub fn scrape_html(body: String) -> String {
@@ -101,83 +214,6 @@ pub fn scrape_html(body: String) -> String {
     return course_content.join("\n");
 }
 
-pub fn extract_timestamps(body: String) -> Vec<Vec<Scene>> {
-    let duration_re = Regex::new(r"^([0-9:]+) - ([0-9:]+)$").unwrap();
-    let time_re = Regex::new(r"^([0-9:]+)$").unwrap();
-
-    let course_content = body.lines() 
-        .map(|s| s.trim())
-        .filter(|s| !s.is_empty())
-        .map(|s| s.to_string())
-        .collect::<Vec<String>>();
- 
-    let titles = &course_content.clone().into_iter()
-        .filter(|t| !time_re.is_match(t) && !duration_re.is_match(t))
-        .collect::<Vec<String>>();
-
-    println!("Titles");
-    titles.clone().into_iter().for_each(|t| println!("{}", t));
-
-    let timestamps = course_content.clone().into_iter()
-        .filter(|t| time_re.is_match(t))
-        .map(|t| time_to_seconds(&t))
-        .collect::<Vec<usize>>();
-
-    let mut durations = course_content.clone().into_iter()
-        .filter(|t| duration_re.is_match(t))
-        .map(|t| t.split(" - ").map(|s| s.to_string()).collect::<Vec<String>>())
-        .map(|a| (time_to_seconds(&a[0].to_string()), time_to_seconds(&a[1].to_string())))
-        .collect::<Vec<(usize, usize)>>();
-
-    if durations.len() == 0 {
-        durations = timestamps.clone().into_iter().zip(timestamps.clone().into_iter().skip(1)).collect::<Vec<(usize, usize)>>();
-    }
-    
-    println!("Durations");
-    durations.clone().into_iter().for_each(|(s,e)| println!("{}-{}", s,e));
-    // Zip titles and durations into a tuple: (title, (start, end)) 
-    let all_scenes = titles.clone().into_iter().zip(durations.clone().into_iter())
-        .enumerate() 
-        .map(|(index, (title, (start, end)))| Scene{index, title, text: "".to_string(), start, end, labels: vec![], file: "".to_string()})
-        .collect::<Vec<Scene>>();
-
-    // Split the vector into a vector of vectors each time `end` is 0.
-  // let result = all_scenes
-  //       .split(|s| (s.start == 0 && s.index != 0) || s.end == 0)
-  //       .map(|v| v.to_vec().into_iter().enumerate().map(|(index, s)| Scene{index, title: s.title, start: s.start, end: s.end, labels: s.labels, file: s.file}).collect_vec())
-  //       .collect::<Vec<Vec<Scene>>>();
-
-    let mut result:Vec<Vec<Scene>> = vec![];
-    let mut v: i32 = -1;
-    let mut index = 0;
-    let mut last_start = MAX;
-
-    println!("All scenes");
-    all_scenes.into_iter().for_each(|s|  {
-        // New volume
-        if s.start < last_start {
-            result.push(vec![]);
-            v+=1;
-            index=0;
-        }
-        last_start = s.start;
-        let clean_title = clean_title(s.title);
-        result[v as usize].push(Scene{index, title: clean_title, text: "".to_string(), start: s.start, end: s.end, labels: s.labels, file: s.file});
-        println!("{} - {}: {}" , result[v as usize][index].start, result[v as usize][index].end, result[v as usize][index].title);
-        index+=1;
-    });
-
-    println!("Scrapped:");
-    for i in 0..result.len() {
-        println!("Volume: {}" , (i+1));
-        for j in 0..result[i].len() {
-            println!("{} - {}: {}" , result[i][j].start, result[i][j].end, result[i][j].title);
-        }
-    }
-
-    return result;
-}
-
 //Reoder the two strings left & right so that they match [title] - [timestamps]
 pub fn check_order_s(left: &str, right: &str) -> (String, String) {
  check_order(left.to_string(), right.to_string())
@@ -199,94 +235,215 @@ pub fn check_order(left: String, right: String) -> (String, String) {
     }
 }
 
-pub fn update_cache(creator: String, title: String)   {
-    let fanatics_dir = get_cache_dir().join("bjj-fanatics");
-    let path = fanatics_dir.join("products.json");
-    std::fs::create_dir_all(&fanatics_dir).unwrap();
+/// Refreshes the product list. A full refresh downloads every page of the store, while an incremental one stops at the first page without new products.
+/// The existing cache is only replaced when the refresh succeeds.
+pub fn update_cache(client: &HttpClient, full: bool) -> Result<Products, HttpError> {
+    let known = if full { vec![] } else { load_products_cache().map(|p| p.products).unwrap_or_default() };
+    let known_ids: Vec<usize> = known.iter().map(|p| p.id).collect();
 
-    // Try to fetch data from cache.
-    if path.exists() {
-       std::fs::remove_file(path).unwrap();
+    let mut fetched: Vec<Product> = Vec::new();
+    let mut page = 1;
+    loop {
+        let page_data = search_product_page(client, page)?;
+        // Newest products come first, so a page of known products means that there is nothing new after it.
+        let has_new = page_data.iter().any(|p| !known_ids.contains(&p.id));
+        let is_empty = page_data.is_empty();
+        fetched.extend(page_data);
+        if is_empty || (!known_ids.is_empty() && !has_new) {
+            break;
+        }
+        page+=1;
     }
 
-    search_product(creator, title);
+    let products = Products { products: merge_products(known, fetched), updated_at: now_in_secs() };
+    println!("Product cache updated: {} products.", products.products.len());
+    save_products_cache(&products);
+    Ok(products)
 }
 
-pub fn search_product(creator: String, title: String) -> Vec<Instructional> {
-    let fanatics_dir = get_cache_dir().join("bjj-fanatics");
-    let path = fanatics_dir.join("products.json");
-    std::fs::create_dir_all(&fanatics_dir).unwrap();
-
-    // Try to fetch data from cache.
-    if path.exists() {
-        let file = File::open(path).unwrap();
-        let reader = BufReader::new(file);
-        let data: Products = serde_json::from_reader(reader).unwrap();
-        return data.products.into_iter()
-        .filter(|p| p.title.contains(creator.as_str()) && p.title.contains(title.as_str()))
-        .map(|p| product_to_instructional(p))
+/// Adds the fetched products to the known ones. Fetched products replace known products with the same id.
+pub fn merge_products(known: Vec<Product>, fetched: Vec<Product>) -> Vec<Product> {
+    let fetched_ids: Vec<usize> = fetched.iter().map(|p| p.id).collect();
+    fetched.into_iter()
+        .chain(known.into_iter().filter(|p| !fetched_ids.contains(&p.id)))
         .collect()
+}
+
+/// The cached product list, refreshed when missing or expired. When the refresh fails the last snapshot is used.
+pub fn get_products(client: &HttpClient) -> Result<Vec<Product>, HttpError> {
+    match load_products_cache() {
+        Some(cached) if !is_products_cache_expired(&cached, now_in_secs()) => Ok(cached.products),
+        Some(cached) => match update_cache(client, false) {
+            Ok(products) => Ok(products.products),
+            Err(e) => {
+                println!("{}. Using cached products.", e);
+                Ok(cached.products)
+            }
+        },
+        None => update_cache(client, true).map(|p| p.products),
     }
+}
 
-    let mut result: Vec<Product> = Vec::new();
-    let mut page = 1;
-    let mut page_data: Vec<Product> = search_product_page(page);
+/// The products matching the creator (against the vendor) and title, best matches first.
+pub fn search_product(client: &HttpClient, creator: String, title: String) -> Result<Vec<Instructional>, HttpError> {
+    Ok(rank_products(client.settings(), get_products(client)?, &creator, &title))
+}
 
-    while !page_data.is_empty() {
-        page_data.into_iter()
-            .for_each(|i| result.push(i));
-        page_data = search_product_page(page);
-        page+=1;
-    }
+/// Like `search_product`, but only the cached products are searched. Nothing is found before the products are downloaded.
+pub fn search_cached_product(settings: &HttpSettings, creator: String, title: String) -> Vec<Instructional> {
+    load_products_cache()
+        .map(|cached| rank_products(settings, cached.products, &creator, &title))
+        .unwrap_or_default()
+}
+
+fn rank_products(settings: &HttpSettings, products: Vec<Product>, creator: &str, title: &str) -> Vec<Instructional> {
+    rank_by_match(products, creator, title, |p| (p.vendor.to_string(), p.title.to_string()))
+        .into_iter()
+        .map(|p| product_to_instructional(settings, p))
+        .collect()
+}
+
+pub fn product_to_instructional(settings: &HttpSettings, product: Product) -> Instructional {
+    let mut instructional = Instructional::new();
+    instructional.url = crate::join_url(&settings.base_url, &format!("products/{}", product.handle));
+    instructional.creator = product.vendor;
+    instructional.title = product.title;
+    instructional.provider = FANATICS_PROVIDER.to_string();
+    instructional.description = product.body_html.map(|b| html_to_plain_text(&b)).unwrap_or_default();
+    instructional.published_at = product.published_at.unwrap_or_default();
+    instructional.tags = product.tags;
+    // Image urls are usually protocol relative (e.g. `//cdn.shopify.com/...`).
+    instructional.cover = product.images.first().map(|i| if i.src.starts_with("//") { format!("https:{}", i.src) } else { i.src.to_string() }).unwrap_or_default();
+    instructional
+}
 
-    if result.is_empty() {
-        return vec![];
+/// The text of the html fragment in a single line.
+pub fn html_to_plain_text(html: &str) -> String {
+    Html::parse_fragment(html).root_element()
+        .text()
+        .flat_map(|t| t.split_whitespace())
+        .join(" ")
+}
+
+/// The cached product with the specified url.
+pub fn find_product(client: &HttpClient, url: &str) -> Result<Option<Instructional>, HttpError> {
+    let handle = url.split('?').next().unwrap_or_default().trim_end_matches('/').split('/').last().unwrap_or_default().to_string();
+    Ok(get_products(client)?.into_iter()
+        .find(|p| p.handle == handle)
+        .map(|p| product_to_instructional(client.settings(), p)))
+}
+
+pub fn search_product_page(client: &HttpClient, page: usize) -> Result<Vec<Product>, HttpError> {
+    let path = format!("products.json?limit=100&page={}", page);
+    let response = client.get(&path)?;
+    if response.trim().is_empty() {
+        return Ok(vec![]);
     }
+    let data: Products = serde_json::from_str(&response).map_err(|e| HttpError::Parse(client.url(&path), e.to_string()))?;
+    Ok(data.products)
+}
 
-    let products = Products{products: result.to_vec()};
-    let file = File::create(path).unwrap();
-    let mut out = BufWriter::new(file);
-    out.write_all(serde_json::to_string(&products).expect("Failed to serialize products!").as_bytes()).expect("Failed to write products to file!");
+pub static FANATICS_PROVIDER: &str = "bjj-fanatics";
+pub static FANATICS_PROVIDER_NAME: &str = "BJJ Fanatics";
 
-    result.into_iter()
-        .filter(|p| p.title.contains(creator.as_str()) && p.title.contains(title.as_str()))
-        .map(|p| product_to_instructional(p))
-        .collect()
+/// Products of bjjfanatics.com, read from the Shopify products feed and the product page chapter table.
+pub struct FanaticsProvider {
+    client: Result<HttpClient, HttpError>,
 }
 
-pub fn product_to_instructional(product: Product) -> Instructional {
-    return Instructional {creator: product.vendor, title: product.title, url: format!("https://bjjfanatics.com/products/{}", product.handle), timestamps: BLANK.to_owned(), videos: vec![] };
-}
-
-pub fn search_product_page(page: usize) -> Vec<Product> {
-    let url = format!("https://bjjfanatics.com/products.json?limit=100&page={}", page).to_string();
-    println!("Searching: {}", url);
-    let client = reqwest::blocking::Client::builder()
-        .cookie_store(false)
-        .build()
-        .ok()
-        .unwrap();
-   let response: String = client.get(url)
-        .header("user-agent", "rust")
-        .header("accept", "*/*")
-        .send()
-        .ok()
-        .map(|r| r.text().ok().unwrap_or_default())
-        .unwrap_or_default();
-
-    println!("Reponse:{}", &response);
-    if response.is_empty() {
-        return vec![];
+impl FanaticsProvider {
+    pub fn new(settings: &HttpSettings) -> FanaticsProvider {
+        FanaticsProvider { client: HttpClient::new(settings) }
+    }
+
+    fn client(&self) -> Result<&HttpClient, String> {
+        self.client.as_ref().map_err(|e| e.to_string())
+    }
+}
+
+impl MetadataProvider for FanaticsProvider {
+    fn id(&self) -> &'static str {
+        FANATICS_PROVIDER
+    }
+
+    fn name(&self) -> &'static str {
+        FANATICS_PROVIDER_NAME
+    }
+
+    fn popular_creators(&self) -> Vec<String> {
+        get_popular_creators()
+    }
+
+    fn list_creators(&self) -> Vec<String> {
+        get_cached_creators()
+    }
+
+    fn search_products(&self, creator: String, title: String) -> Result<Vec<Instructional>, String> {
+        search_product(self.client()?, creator, title).map_err(|e| e.to_string())
     }
-    let data: Products = serde_json::from_str(&response).unwrap();
-    let products = &data.products;
 
-    products.iter().cloned().collect()
+    fn search_cached_products(&self, creator: String, title: String) -> Result<Vec<Instructional>, String> {
+        Ok(search_cached_product(self.client()?.settings(), creator, title))
+    }
+
+    fn fetch_chapters(&self, url: String) -> Result<String, String> {
+        scrape_url(self.client()?, url).map_err(|e| e.to_string())
+    }
+
+    fn fetch_details(&self, url: String) -> Result<Option<Instructional>, String> {
+        find_product(self.client()?, &url).map_err(|e| e.to_string())
+    }
+
+    fn download_cover(&self, url: String) -> Result<PathBuf, String> {
+        crate::download_cover(self.client()?, &url).map_err(|e| e.to_string())
+    }
+
+    fn refresh(&self) -> Result<(), String> {
+        update_cache(self.client()?, false).map(|_| ()).map_err(|e| e.to_string())
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    fn product(id: usize, title: &str) -> Product {
+        Product { id, vendor: "iocanel".to_string(), title: title.to_string(), handle: title.to_lowercase(), ..Default::default() }
+    }
+
+    #[test]
+    fn test_product_to_instructional() {
+        let product: Product = serde_json::from_str(r#"{"id": 1, "vendor": "John Danaher", "title": "Back Attacks", "handle": "back-attacks",
+            "body_html": "<p>Learn the <b>back</b>\n system.</p>", "published_at": "2021-03-01T00:00:00-05:00", "tags": ["gi", "no-gi"],
+            "images": [{"id": 2, "src": "//cdn.example.com/back.jpg"}]}"#).unwrap();
+        let instructional = product_to_instructional(&HttpSettings::new(), product);
+        assert_eq!("https://bjjfanatics.com/products/back-attacks", instructional.url);
+        assert_eq!("Learn the back system.", instructional.description);
+        assert_eq!(vec!["gi".to_string(), "no-gi".to_string()], instructional.tags);
+        assert_eq!("https://cdn.example.com/back.jpg", instructional.cover);
+
+        // Products cached before metadata was stored.
+        let product: Product = serde_json::from_str(r#"{"id": 1, "vendor": "John Danaher", "title": "Back Attacks", "handle": "back-attacks", "body_html": null}"#).unwrap();
+        assert!(product_to_instructional(&HttpSettings::new(), product).description.is_empty());
+    }
+
+    #[test]
+    fn test_merge_products() {
+        let known = vec![product(2, "Old"), product(1, "Oldest")];
+        let fetched = vec![product(3, "New"), product(2, "Old Updated")];
+        let merged = merge_products(known, fetched);
+        assert_eq!(vec![3, 2, 1], merged.iter().map(|p| p.id).collect::<Vec<usize>>());
+        assert_eq!("Old Updated", merged[1].title);
+    }
+
+    #[test]
+    fn test_products_cache_expiry() {
+        let products = Products { products: vec![], updated_at: 1000 };
+        assert!(!is_products_cache_expired(&products, 1000 + PRODUCTS_TTL));
+        assert!(is_products_cache_expired(&products, 1001 + PRODUCTS_TTL));
+        // Caches without a timestamp are always expired.
+        assert!(is_products_cache_expired(&Products { products: vec![], updated_at: 0 }, now_in_secs()));
+    }
+
     #[test]
     fn test_reorder() {
         assert_eq!(("scene 1".to_string(), "00:00:00".to_string()), check_order_s("scene 1", "00:00:00"));
@@ -0,0 +1,222 @@
+use std::fmt;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use crate::get_data_dir;
+
+/// The settings of the HTTP client used for scraping.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct HttpSettings {
+    // The url relative paths are resolved against (e.g. a local mirror or test server).
+    pub base_url: String,
+    pub user_agent: String,
+    pub timeout_secs: u64,
+    // How many times a failed request is retried.
+    pub retries: u32,
+    // The delay before the first retry. It's doubled on every retry.
+    pub backoff_millis: u64,
+    // The minimum time between two consecutive requests.
+    pub min_interval_millis: u64,
+}
+
+impl HttpSettings {
+    pub fn new() -> Self {
+        HttpSettings {
+            base_url: "https://bjjfanatics.com".to_string(),
+            user_agent: "hg2jj".to_string(),
+            timeout_secs: 30,
+            retries: 3,
+            backoff_millis: 500,
+            min_interval_millis: 1000,
+        }
+    }
+}
+
+impl Default for HttpSettings {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum HttpError {
+    Client(String),
+    Timeout(String),
+    Connection(String, String),
+    Status(String, u16),
+    Body(String, String),
+    Parse(String, String),
+}
+
+impl fmt::Display for HttpError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            HttpError::Client(e) => write!(f, "Failed to create HTTP client: {}", e),
+            HttpError::Timeout(url) => write!(f, "Request to {} timed out", url),
+            HttpError::Connection(url, e) => write!(f, "Failed to connect to {}: {}", url, e),
+            HttpError::Status(url, status) => write!(f, "Request to {} failed with status {}", url, status),
+            HttpError::Body(url, e) => write!(f, "Failed to read response of {}: {}", url, e),
+            HttpError::Parse(url, e) => write!(f, "Failed to parse response of {}: {}", url, e),
+        }
+    }
+}
+
+impl std::error::Error for HttpError {}
+
+/// A blocking HTTP client that retries failed requests with exponential backoff and keeps a minimum interval between requests.
+/// The client can be shared between threads, the interval is kept across all of them.
+pub struct HttpClient {
+    settings: HttpSettings,
+    client: reqwest::blocking::Client,
+    last_request: Mutex<Option<Instant>>,
+}
+
+impl HttpClient {
+    pub fn new(settings: &HttpSettings) -> Result<HttpClient, HttpError> {
+        let client = reqwest::blocking::Client::builder()
+            .cookie_store(false)
+            .user_agent(settings.user_agent.to_string())
+            .timeout(Duration::from_secs(settings.timeout_secs))
+            .build()
+            .map_err(|e| HttpError::Client(e.to_string()))?;
+        Ok(HttpClient { settings: settings.clone(), client, last_request: Mutex::new(None) })
+    }
+
+    pub fn settings(&self) -> &HttpSettings {
+        &self.settings
+    }
+
+    /// Resolves the path against the base url. Absolute urls are returned as is.
+    pub fn url(&self, path: &str) -> String {
+        join_url(&self.settings.base_url, path)
+    }
+
+    /// Fetches the body of the url (or path relative to the base url).
+    pub fn get(&self, path: &str) -> Result<String, HttpError> {
+        let url = self.url(path);
+        self.with_retries(&url, |response| response.text())
+    }
+
+    /// Fetches the body of the url (or path relative to the base url) as bytes, e.g. for images.
+    pub fn get_bytes(&self, path: &str) -> Result<Vec<u8>, HttpError> {
+        let url = self.url(path);
+        self.with_retries(&url, |response| response.bytes().map(|b| b.to_vec()))
+    }
+
+    fn with_retries<T, F>(&self, url: &str, read: F) -> Result<T, HttpError> where F: Fn(reqwest::blocking::Response) -> reqwest::Result<T> {
+        let mut attempt = 0;
+        loop {
+            self.wait_for_turn();
+            println!("Fetching: {}", url);
+            match self.try_get(url).and_then(|response| read(response).map_err(|e| HttpError::Body(url.to_string(), e.to_string()))) {
+                Ok(body) => return Ok(body),
+                Err(e) if attempt < self.settings.retries && is_retryable(&e) => {
+                    let delay = backoff_delay(attempt, self.settings.backoff_millis);
+                    println!("{}. Retrying in {} ms.", e, delay.as_millis());
+                    std::thread::sleep(delay);
+                    attempt += 1;
+                },
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    fn try_get(&self, url: &str) -> Result<reqwest::blocking::Response, HttpError> {
+        let response = self.client.get(url)
+            .header("accept", "*/*")
+            .send()
+            .map_err(|e| if e.is_timeout() { HttpError::Timeout(url.to_string()) } else { HttpError::Connection(url.to_string(), e.to_string()) })?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(HttpError::Status(url.to_string(), status.as_u16()));
+        }
+        Ok(response)
+    }
+
+    fn wait_for_turn(&self) {
+        let interval = Duration::from_millis(self.settings.min_interval_millis);
+        // The lock is held while waiting, so that threads take turns.
+        let mut last_request = self.last_request.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(last) = *last_request {
+            let elapsed = last.elapsed();
+            if elapsed < interval {
+                std::thread::sleep(interval - elapsed);
+            }
+        }
+        *last_request = Some(Instant::now());
+    }
+}
+
+pub fn join_url(base_url: &str, path: &str) -> String {
+    if path.starts_with("http://") || path.starts_with("https://") || base_url.is_empty() {
+        return path.to_string();
+    }
+    format!("{}/{}", base_url.trim_end_matches('/'), path.trim_start_matches('/'))
+}
+
+/// Timeouts, connection errors, rate limiting and server errors are worth retrying. Other errors are not going to go away.
+fn is_retryable(error: &HttpError) -> bool {
+    match error {
+        HttpError::Timeout(_) | HttpError::Connection(_, _) | HttpError::Body(_, _) => true,
+        HttpError::Status(_, status) => *status == 429 || *status >= 500,
+        _ => false,
+    }
+}
+
+fn backoff_delay(attempt: u32, backoff_millis: u64) -> Duration {
+    Duration::from_millis(backoff_millis.saturating_mul(1u64 << attempt.min(16)))
+}
+
+pub fn get_http_settings_file() -> PathBuf {
+    get_data_dir().join("http-settings.json")
+}
+
+pub fn load_http_settings() -> HttpSettings {
+    let path = get_http_settings_file();
+    if !path.exists() {
+        return HttpSettings::new();
+    }
+    let file = File::open(&path).expect("Failed to open HTTP settings file!");
+    let reader = BufReader::new(file);
+    match serde_json::from_reader::<_, HttpSettings>(reader) {
+        Ok(settings) => settings,
+        Err(e) => {
+            println!("Failed to read HTTP settings from: {}. Using defaults. Error: {}", path.display(), e);
+            HttpSettings::new()
+        }
+    }
+}
+
+pub fn save_http_settings(settings: &HttpSettings) {
+    let path = get_http_settings_file();
+    std::fs::create_dir_all(path.parent().expect("Failed to find data dir!")).expect("Failed to create data dir!");
+    let file = File::create(path).expect("Failed to create HTTP settings file!");
+    let mut out = BufWriter::new(file);
+    out.write_all(serde_json::to_string_pretty(settings).expect("Failed to serialize HTTP settings!").as_bytes()).expect("Failed to write HTTP settings!");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_join_url() {
+        assert_eq!("https://bjjfanatics.com/products.json", join_url("https://bjjfanatics.com", "products.json"));
+        assert_eq!("https://bjjfanatics.com/products.json", join_url("https://bjjfanatics.com/", "/products.json"));
+        assert_eq!("http://localhost:8080/products/x", join_url("https://bjjfanatics.com", "http://localhost:8080/products/x"));
+        assert_eq!("/products.json", join_url("", "/products.json"));
+    }
+
+    #[test]
+    fn test_retries() {
+        assert!(is_retryable(&HttpError::Status("".to_string(), 429)));
+        assert!(is_retryable(&HttpError::Status("".to_string(), 503)));
+        assert!(!is_retryable(&HttpError::Status("".to_string(), 404)));
+        assert!(!is_retryable(&HttpError::Parse("".to_string(), "".to_string())));
+        assert_eq!(Duration::from_millis(500), backoff_delay(0, 500));
+        assert_eq!(Duration::from_millis(2000), backoff_delay(2, 500));
+    }
+}
@@ -0,0 +1,170 @@
+use std::path::Path;
+use itertools::Itertools;
+use regex::Regex;
+use crate::Instructional;
+use crate::query_score;
+
+/// What could be guessed about an instructional from the name of a video file (and its folder).
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileGuess {
+    pub creator: Option<String>,
+    pub title: String,
+    pub volume: Option<usize>,
+}
+
+/// Guesses creator, title and volume from a path like `John Danaher - Back Attacks Enter The System Vol 3.mp4`.
+/// When the file name doesn't contain the creator, the name of the parent folder is used.
+pub fn guess_from_file(file: &str) -> FileGuess {
+    let path = Path::new(file);
+    let stem = path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+    let (name, volume) = extract_volume(&to_words(&stem));
+    let (creator, title) = split_creator(&name);
+
+    let folder = path.parent()
+        .and_then(|p| p.file_name())
+        .map(|f| to_words(&f.to_string_lossy()))
+        .unwrap_or_default();
+    let (folder, _) = extract_volume(&folder);
+    let (folder_creator, folder_title) = split_creator(&folder);
+
+    let creator = creator
+        .or(folder_creator)
+        // A folder with just a name in it, is most likely named after the creator.
+        .or_else(|| if looks_like_name(&folder_title) { Some(folder_title.to_string()) } else { None });
+    let title = if title.is_empty() { folder_title } else { title };
+    FileGuess { creator, title, volume }
+}
+
+/// Guesses the creator and title shared by all the files (the most common ones).
+pub fn guess_from_files(files: &Vec<String>) -> Option<FileGuess> {
+    let guesses: Vec<FileGuess> = files.iter().map(|f| guess_from_file(f)).collect();
+    let creator = most_common(guesses.iter().filter_map(|g| g.creator.clone()).collect());
+    let title = most_common(guesses.iter().map(|g| g.title.to_string()).filter(|t| !t.is_empty()).collect());
+    if creator.is_none() && title.is_none() {
+        return None;
+    }
+    Some(FileGuess { creator, title: title.unwrap_or_default(), volume: None })
+}
+
+/// How confident we are (from 0.0 to 1.0) that the product is the instructional the guess refers to.
+pub fn identification_confidence(guess: &FileGuess, product: &Instructional) -> f32 {
+    let title_score = query_score(&guess.title, &format!("{} {}", product.title, product.creator)).unwrap_or(0.0);
+    match guess.creator.as_ref().and_then(|c| query_score(c, &product.creator)) {
+        Some(creator_score) => (creator_score + title_score) / 2.0,
+        None => title_score,
+    }
+}
+
+/// The order of the files by volume number. Files without a volume number keep their relative order and go last.
+pub fn volume_order(files: &Vec<String>) -> Vec<usize> {
+    let volumes: Vec<Option<usize>> = files.iter().map(|f| guess_from_file(f).volume).collect();
+    (0..files.len())
+        .sorted_by_key(|i| (volumes[*i].is_none(), volumes[*i].unwrap_or_default(), *i))
+        .collect()
+}
+
+/// Replaces the separators that are commonly used in file names with spaces.
+fn to_words(text: &str) -> String {
+    text.replace('_', " ")
+        .replace('.', " ")
+        .split_whitespace()
+        .join(" ")
+}
+
+struct Patterns {
+    marker: Regex,
+    trailing: Regex,
+    leading: Regex,
+}
+
+thread_local! {
+    // Compiled once (per thread), as the volume is extracted for every file (and its folder).
+    static PATTERNS: Patterns = Patterns::new();
+}
+
+impl Patterns {
+    fn new() -> Patterns {
+        Patterns {
+            marker: Regex::new(r"(?i)(?:^|[\s\-(\[])(?:volume|vol|part|pt|disc|disk|dvd|v)\s*#?\s*(\d{1,2})\b[)\]]?").unwrap(),
+            trailing: Regex::new(r"[\s\-]+(\d{1,2})$").unwrap(),
+            leading: Regex::new(r"^(\d{1,2})(?:\s*[\-.)]\s*|\s+)").unwrap(),
+        }
+    }
+}
+
+/// Extracts the volume number (e.g. `Vol 3`, `Volume3`, `Part 2`, `Disc 1`, `V3` or a trailing / leading number) and returns the rest of the name.
+fn extract_volume(name: &str) -> (String, Option<usize>) {
+    PATTERNS.with(|patterns| extract_volume_with(name, patterns))
+}
+
+fn extract_volume_with(name: &str, patterns: &Patterns) -> (String, Option<usize>) {
+    for re in [&patterns.marker, &patterns.trailing, &patterns.leading] {
+        if let Some(c) = re.captures(name) {
+            let m = c.get(0).unwrap();
+            let volume = c[1].parse::<usize>().ok();
+            let rest = format!("{} {}", &name[..m.start()], &name[m.end()..]);
+            return (rest.split_whitespace().join(" ").trim_matches(|c: char| c == '-' || c.is_whitespace()).to_string(), volume);
+        }
+    }
+    (name.to_string(), None)
+}
+
+/// Splits `Creator - Title` into its parts. Names without a separator are considered titles.
+fn split_creator(name: &str) -> (Option<String>, String) {
+    match name.split_once(" - ") {
+        Some((left, right)) if looks_like_name(left.trim()) => (Some(left.trim().to_string()), right.trim().to_string()),
+        Some((left, right)) if looks_like_name(right.trim()) => (Some(right.trim().to_string()), left.trim().to_string()),
+        _ => (None, name.trim().to_string()),
+    }
+}
+
+/// Two to four words without digits, e.g. `John Danaher` or `Marcus Buchecha Almeida`.
+fn looks_like_name(text: &str) -> bool {
+    let words: Vec<&str> = text.split_whitespace().collect();
+    words.len() >= 2 && words.len() <= 4 && !text.chars().any(|c| c.is_ascii_digit())
+}
+
+fn most_common(values: Vec<String>) -> Option<String> {
+    values.into_iter()
+        .sorted()
+        .group_by(|v| v.to_string())
+        .into_iter()
+        .map(|(v, group)| (group.count(), v))
+        .max_by_key(|(count, _)| *count)
+        .map(|(_, v)| v)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_guess_from_file() {
+        assert_eq!(FileGuess { creator: Some("John Danaher".to_string()), title: "Back Attacks Enter The System".to_string(), volume: Some(3) },
+                   guess_from_file("/videos/John Danaher - Back Attacks Enter The System Vol 3.mp4"));
+        assert_eq!(FileGuess { creator: Some("Lachlan Giles".to_string()), title: "Half Guard".to_string(), volume: Some(2) },
+                   guess_from_file("/videos/Lachlan Giles/Half_Guard_Part2.mp4"));
+        assert_eq!(FileGuess { creator: Some("Gordon Ryan".to_string()), title: "The Back System".to_string(), volume: Some(1) },
+                   guess_from_file("/videos/Gordon Ryan - The Back System/01 - The Back System.mkv"));
+        assert_eq!(None, guess_from_file("/videos/Leglocks 4.mp4").creator);
+        assert_eq!(Some(4), guess_from_file("/videos/Leglocks 4.mp4").volume);
+    }
+
+    #[test]
+    fn test_volume_order() {
+        let files = vec!["/v/Back Attacks Vol 3.mp4".to_string(), "/v/Bonus.mp4".to_string(), "/v/Back Attacks Vol 1.mp4".to_string(), "/v/Back Attacks Vol 2.mp4".to_string()];
+        assert_eq!(vec![2, 3, 0, 1], volume_order(&files));
+    }
+
+    #[test]
+    fn test_identification_confidence() {
+        let guess = guess_from_files(&vec!["/v/John Danaher - Back Attacks Vol 1.mp4".to_string(), "/v/John Danaher - Back Attacks Vol 2.mp4".to_string()]).unwrap();
+        let mut product = Instructional::new();
+        product.creator = "John Danaher".to_string();
+        product.title = "Back Attacks: Enter The System By John Danaher".to_string();
+        assert_eq!(1.0, identification_confidence(&guess, &product));
+        product.creator = "Gordon Ryan".to_string();
+        product.title = "Leglocks By Gordon Ryan".to_string();
+        assert!(identification_confidence(&guess, &product) < 0.5);
+    }
+}
@@ -1,10 +1,26 @@
 #![allow(unused_imports)]
 #![allow(dead_code)]
 mod app;
+mod autotune;
+mod capture;
+mod edl;
+mod chapters;
 mod fanatics;
+mod http;
+mod identify;
+mod local;
 mod mpvstate;
-use opencv::core::{bitwise_not, BORDER_CONSTANT, Size_, NORM_L1};
-use opencv::photo::{fast_nl_means_denoising_vec};
+mod ocr;
+mod player;
+mod presets;
+mod preview;
+mod provider;
+mod reconcile;
+mod search;
+mod session;
+mod similarity;
+mod timestamps;
+mod vlc;
 use platform_dirs::AppDirs;
 use regex::Regex;
 use spellcheck::Speller;
@@ -21,8 +37,26 @@ use ffprobe::*;
 use itertools::Itertools;
 use mpvipc::Mpv;
 pub use app::App;
+pub use autotune::*;
+pub use capture::*;
+pub use edl::*;
+pub use chapters::*;
 pub use fanatics::*;
+pub use http::*;
+pub use identify::*;
+pub use local::*;
 pub use mpvstate::*;
+pub use ocr::*;
+pub use player::*;
+pub use presets::*;
+pub use preview::*;
+pub use provider::*;
+pub use reconcile::*;
+pub use search::*;
+pub use session::*;
+pub use similarity::*;
+pub use timestamps::*;
+pub use vlc::*;
 
 use opencv::{
     imgcodecs::*,
@@ -37,7 +71,32 @@ pub struct Instructional {
     title: String,
     url: String,
     timestamps: String,
-    videos: Vec<Video>
+    videos: Vec<Video>,
+    // The id of the metadata provider the instructional was found with.
+    provider: String,
+    // Product metadata (all optional).
+    description: String,
+    published_at: String,
+    tags: Vec<String>,
+    // The url of the cover image.
+    cover: String,
+}
+
+impl Instructional {
+    fn new() -> Self {
+        Instructional {
+            creator: BLANK.to_owned(),
+            title: BLANK.to_owned(),
+            url: BLANK.to_owned(),
+            timestamps: BLANK.to_owned(),
+            videos: vec![],
+            provider: DEFAULT_PROVIDER.to_owned(),
+            description: BLANK.to_owned(),
+            published_at: BLANK.to_owned(),
+            tags: vec![],
+            cover: BLANK.to_owned(),
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -56,7 +115,9 @@ pub struct Scene {
     text: String,
     labels: Vec<String>,
     start: usize,
-    end: usize
+    end: usize,
+    // The preferred playback speed (1.0 is normal speed).
+    speed: f64
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -91,6 +152,10 @@ pub struct GeneralSettings {
     md_export_filename: String,
     playlist_export_enabled: bool,
     playlist_export_filename: String,
+    // The media player backend (e.g. `mpv` or `vlc`).
+    player: String,
+    // The mpv ipc socket or the VLC RC address. When empty, one unique to the session is used.
+    player_socket: String,
     case: Case
 }
 
@@ -104,6 +169,8 @@ impl GeneralSettings {
             md_export_filename: "readme.md".to_string(),
             playlist_export_enabled: true,
             playlist_export_filename: "playlist.m3u".to_string(),
+            player: MPV_BACKEND.to_string(),
+            player_socket: "".to_string(),
             case: Case::CapitalizeFirst
         }
     }
@@ -116,57 +183,31 @@ pub enum Case {
     CapitalizeFirst,
 }
 
-#[derive(Debug, Clone)]
+/// Settings are read through `SavedOcrSettings`, so that settings saved before the preprocessing steps are migrated.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(from = "SavedOcrSettings")]
 pub struct OcrSettings {
-    grayscale: bool,
-
-    gaussian_thresholding: bool,
-    gaussian_thresholding_max_value: f64,
-    gaussian_thresholding_blocksize: i32,
-    gaussian_thresholding_c: f64,
-
-    otsu_thresholding: bool,
-    otsu_thresholding_min_value: f64,
-    otsu_thresholding_max_value: f64,
-
-    denoise: bool,
-    denoise_strength: f32,
-
-    erode: bool,
-    erode_kernel_size: i32,
-    erode_iterations: i32,
-    dilate: bool,
-    dilate_kernel_size: i32,
-    dilate_iterations: i32,
-
-    invert: bool,
-    spellcheking: bool
+    // The region of interest is expressed as a `Crop` step.
+    steps: Vec<OcrStep>,
+    spellcheking: bool,
+    language: String
 }
 impl OcrSettings {
     fn new() -> Self {
         OcrSettings {
-            grayscale: true,
-            invert: true,
-            gaussian_thresholding: false,
-            gaussian_thresholding_max_value: 255.0,
-            gaussian_thresholding_blocksize: 11,
-            gaussian_thresholding_c: 2.0,
-            otsu_thresholding: false,
-            otsu_thresholding_min_value: 0.0,
-            otsu_thresholding_max_value: 255.0,
-            denoise: true,
-            denoise_strength: 3.0,
-            erode: true,
-            erode_kernel_size:3,
-            erode_iterations:1,
-            dilate: true,
-            dilate_kernel_size:3,
-            dilate_iterations:1,
+            steps: default_ocr_steps(),
             spellcheking: true,
+            language: "eng".to_string(),
         }
     }
 }
 
+impl Default for OcrSettings {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 static BLANK: &str = "";
 
 pub fn load_org(mut f: File) -> Instructional {
@@ -182,11 +223,17 @@ pub fn parse_org(content: String) -> Instructional {
     let title_re = Regex::new(r"#\+title: (.*)").unwrap();
     let creator_re = Regex::new(r"#\+creator: (.*)").unwrap();
     let url_re = Regex::new(r"#\+url: (.*)").unwrap();
+    let provider_re = Regex::new(r"#\+provider: (.*)").unwrap();
+    let description_re = Regex::new(r"#\+description: (.*)").unwrap();
+    let published_re = Regex::new(r"#\+published: (.*)").unwrap();
+    let tags_re = Regex::new(r"#\+tags: (.*)").unwrap();
+    let cover_re = Regex::new(r"#\+cover: (.*)").unwrap();
 
     let volume_re = Regex::new(r"^\*+ Volume [0-9]+$").unwrap();
     let s_title_re = Regex::new(r"^\*+ ([a-zA-Z0-9'`\.,_ /&:-]+) (:[a-zA-Z0-9_-]+:)$").unwrap();
     let start_timestamp_re = Regex::new(r":START_TIMESTAMP:[ ]+([0-9]+)").unwrap();
     let end_timestamp_re = Regex::new(r":END_TIMESTAMP:[ ]*([0-9]+)").unwrap();
+    let speed_re = Regex::new(r":SPEED:[ ]*([0-9]+(\.[0-9]+)?)").unwrap();
     let properties_re = Regex::new(r":PROPERTIES:").unwrap();
     let end_re = Regex::new(r":END:").unwrap();
     let file_re = Regex::new(r":FILE_OR_URL:(.+)$").unwrap();
@@ -198,6 +245,11 @@ pub fn parse_org(content: String) -> Instructional {
     let mut creator=String::from("unknown");
     let mut title=String::from("unknown");
     let mut url=String::from("");
+    let mut provider=String::from(DEFAULT_PROVIDER);
+    let mut description=String::from("");
+    let mut published_at=String::from("");
+    let mut tags: Vec<String> = vec![];
+    let mut cover=String::from("");
     
     //Scene 
     let mut index: usize = 1;
@@ -208,6 +260,7 @@ pub fn parse_org(content: String) -> Instructional {
     let mut labels: Vec<String> = Vec::new();
     let mut start: Option<usize> = None;
     let mut end: Option<usize> = None;
+    let mut speed: Option<f64> = None;
     let mut in_properties: bool = false;
     let mut after_properties: bool = false;
     let mut line_number: usize = 0;
@@ -233,9 +286,35 @@ pub fn parse_org(content: String) -> Instructional {
             url = cap.get(1).map(|m| m.as_str().to_string()).expect("Failed to caputre url!");
         }
 
+        else if provider_re.is_match(&line) {
+            let cap = provider_re.captures(&line).expect("Failed to match regex!");
+            provider = cap.get(1).map(|m| m.as_str().trim().to_string()).expect("Failed to caputre provider!");
+        }
+
+        else if description_re.is_match(&line) {
+            let cap = description_re.captures(&line).expect("Failed to match regex!");
+            description = cap.get(1).map(|m| m.as_str().trim().to_string()).expect("Failed to caputre description!");
+        }
+
+        else if published_re.is_match(&line) {
+            let cap = published_re.captures(&line).expect("Failed to match regex!");
+            published_at = cap.get(1).map(|m| m.as_str().trim().to_string()).expect("Failed to caputre published date!");
+        }
+
+        else if tags_re.is_match(&line) {
+            let cap = tags_re.captures(&line).expect("Failed to match regex!");
+            tags = cap.get(1).map(|m| m.as_str().split(',').map(|t| t.trim().to_string()).filter(|t| !t.is_empty()).collect()).expect("Failed to caputre tags!");
+        }
+
+        else if cover_re.is_match(&line) {
+            let cap = cover_re.captures(&line).expect("Failed to match regex!");
+            cover = cap.get(1).map(|m| m.as_str().trim().to_string()).expect("Failed to caputre cover!");
+        }
+
         else if volume_re.is_match(&line) {
             start = None;
             end = None;
+            speed = None;
             index = 0;
             s_title = String::new();
             text = String::new();
@@ -259,6 +338,11 @@ pub fn parse_org(content: String) -> Instructional {
             end = cap.get(1).map(|m| m.as_str().parse::<usize>().expect("Failed to parse end timestamp!"));
         }
 
+        else if speed_re.is_match(&line) {
+            let cap = speed_re.captures(&line).expect("Failed to match regex!");
+            speed = cap.get(1).map(|m| m.as_str().parse::<f64>().expect("Failed to parse speed!"));
+        }
+
         else if file_re.is_match(&line) {
             let cap = file_re.captures(&line).expect("Failed to match regex!");
             file = cap.get(1)
@@ -300,7 +384,8 @@ pub fn parse_org(content: String) -> Instructional {
                     file,
                     text: text.trim().to_string(),
                     start: start.unwrap_or(0),
-                    end: end.unwrap_or(0)});
+                    end: end.unwrap_or(0),
+                    speed: speed.unwrap_or(1.0)});
 
                 index += 1;
                 in_properties = false;
@@ -311,6 +396,7 @@ pub fn parse_org(content: String) -> Instructional {
                 labels = vec![];
                 start = None;
                 end = None;
+                speed = None;
                 text = String::new();
         }
     }
@@ -332,7 +418,7 @@ pub fn parse_org(content: String) -> Instructional {
            return v;
         }).collect();
     
-   Instructional{creator, title, url, timestamps: BLANK.to_owned(), videos}
+   Instructional{creator, title, url, timestamps: BLANK.to_owned(), videos, provider, description, published_at, tags, cover}
 }
 
 pub fn save_playlist(instructional: &mut Instructional, out: File) {
@@ -353,6 +439,19 @@ pub fn save_org(instructional: &mut Instructional, out: File, absolute: bool) {
     out.write_all(format!("#+creator: {}\n", instructional.creator).as_bytes()).expect("Unable to write creator!");
     out.write_all(format!("#+title: {}\n", clean_title(instructional.title.to_string())).as_bytes()).expect("Unable to write title!");
     out.write_all(format!("#+url: {}\n", instructional.url).as_bytes()).expect("Unable to write title!");
+    out.write_all(format!("#+provider: {}\n", instructional.provider).as_bytes()).expect("Unable to write provider!");
+    if !instructional.description.is_empty() {
+        out.write_all(format!("#+description: {}\n", instructional.description).as_bytes()).expect("Unable to write description!");
+    }
+    if !instructional.published_at.is_empty() {
+        out.write_all(format!("#+published: {}\n", instructional.published_at).as_bytes()).expect("Unable to write published date!");
+    }
+    if !instructional.tags.is_empty() {
+        out.write_all(format!("#+tags: {}\n", instructional.tags.join(", ")).as_bytes()).expect("Unable to write tags!");
+    }
+    if !instructional.cover.is_empty() {
+        out.write_all(format!("#+cover: {}\n", instructional.cover).as_bytes()).expect("Unable to write cover!");
+    }
     out.write_all("\n".as_bytes()).expect("Unable to write separator line!");
     instructional.videos.iter().for_each(|v| {
         out.write_all(format!("** Volume {}\n", v.index + 1).as_bytes()).expect("Unable to write video entry!");
@@ -371,6 +470,9 @@ pub fn save_org(instructional: &mut Instructional, out: File, absolute: bool) {
             out.write_all(format!(":FILE_OR_URL: {}\n", file_or_url).as_bytes()).expect("Unable to write scene file or url!");
             out.write_all(format!(":START_TIMESTAMP: {}\n", s.start).as_bytes()).expect("Unable to write scene start timestamp!");
             out.write_all(format!(":END_TIMESTAMP: {}\n", s.end).as_bytes()).expect("Unable to write scene end timestamp!");
+            if s.speed != 1.0 {
+                out.write_all(format!(":SPEED: {}\n", s.speed).as_bytes()).expect("Unable to write scene speed!");
+            }
             out.write_all(":END:\n".as_bytes()).expect("Unable to write scene properties end!");
             out.write_all(format!("{}\n", s.text).as_bytes()).expect("Unable to write scene text!");
             out.write_all("\n".as_bytes()).expect("Unable to write separator line!");
@@ -379,8 +481,18 @@ pub fn save_org(instructional: &mut Instructional, out: File, absolute: bool) {
     });
 }
 
-pub fn save_md(instructional: &mut Instructional, out: File) {
+/// Saves the instructional as markdown. The cover is linked only if it exists next to the markdown file (`with_cover`).
+pub fn save_md(instructional: &mut Instructional, out: File, with_cover: bool) {
     let mut out = BufWriter::new(out);
+    if with_cover && !instructional.cover.is_empty() {
+        out.write_all(format!("![{}]({})\n\n", instructional.title, cover_file_name(&instructional.cover)).as_bytes()).expect("Unable to write cover!");
+    }
+    if !instructional.description.is_empty() {
+        out.write_all(format!("{}\n\n", instructional.description).as_bytes()).expect("Unable to write description!");
+    }
+    if !instructional.tags.is_empty() {
+        out.write_all(format!("Tags: {}\n\n", instructional.tags.join(", ")).as_bytes()).expect("Unable to write tags!");
+    }
     instructional.videos.iter().for_each(|v| {
         let has_multiple_scenes_per_video = instructional.videos.iter().filter(|v| v.scenes.len() > 1).count() > 0;
         if has_multiple_scenes_per_video {
@@ -432,6 +544,46 @@ pub fn scene_detect(path: String, settings: DetectionSettings) -> Vec<(usize, f3
                  ,c.get(2).map(|m| m.as_str().parse::<f32>().expect("Failed to parse timestamp!")).expect("Failed to match timestamp group!") * 100.0)).collect();
 }
 
+/// Where the cover image with the specified url is cached.
+pub fn cover_img_path(url: &str) -> PathBuf {
+    let covers_dir = get_cache_dir().join("covers");
+    std::fs::create_dir_all(&covers_dir).expect("Failed to create covers directory!");
+    covers_dir.join(cover_cache_name(url))
+}
+
+/// The name of the cached cover. CDN file names (e.g. `1.jpg`) are shared across products, so the name is keyed by a hash of the full url.
+pub fn cover_cache_name(url: &str) -> String {
+    // FNV-1a, so that the name doesn't change between builds.
+    let hash = url.bytes().fold(0xcbf29ce484222325u64, |hash, b| (hash ^ b as u64).wrapping_mul(0x100000001b3));
+    let name = url.split('?').next().unwrap_or_default().split('/').last().unwrap_or_default();
+    if name.is_empty() {
+        format!("{:016x}-cover", hash)
+    } else {
+        format!("{:016x}-{}", hash, name)
+    }
+}
+
+/// The name of the cover image when exported next to the instructional, e.g. `cover.jpg`.
+pub fn cover_file_name(url: &str) -> String {
+    let name = url.split('?').next().unwrap_or_default().split('/').last().unwrap_or_default();
+    match Path::new(name).extension() {
+        Some(ext) => format!("cover.{}", ext.to_string_lossy()),
+        None => "cover.jpg".to_string(),
+    }
+}
+
+/// Downloads the cover image into the cache (unless already cached) and returns its path.
+pub fn download_cover(client: &HttpClient, url: &str) -> Result<PathBuf, HttpError> {
+    let path = cover_img_path(url);
+    if path.exists() {
+        return Ok(path);
+    }
+    let bytes = client.get_bytes(url)?;
+    let mut f = File::create(&path).expect("Failed to create cover image file!");
+    f.write_all(&bytes).expect("Failed to write cover image!");
+    Ok(path)
+}
+
 pub fn scene_img_path(creator: String, title: String, scene: &Scene) -> Option<PathBuf> {
     let path = Path::new(&scene.file);
     let file_name = path.file_name()?;
@@ -498,6 +650,40 @@ pub fn all_scenes(instructional: Instructional) -> Vec<Scene> {
     return result;
 }
 
+/// The scene (video and scene index) that contains the playback time of the file.
+/// The file is matched by path and, as a player launched outside the app may use a relative path, by file name.
+pub fn find_playing_scene(instructional: &Instructional, path: &str, playback_time: f64) -> Option<(usize, usize)> {
+    let file_name = |f: &str| Path::new(f).file_name().map(|n| n.to_os_string());
+    let matches_path = |f: &str| f == path;
+    let matches_name = |f: &str| file_name(f).is_some() && file_name(f) == file_name(path);
+    let find = |matches: &dyn Fn(&str) -> bool| {
+        instructional.videos.iter().enumerate()
+            .flat_map(|(i, v)| v.scenes.iter().enumerate().map(move |(j, s)| (i, j, s)))
+            .filter(|(_, _, s)| matches(&s.file))
+            .filter(|(_, _, s)| s.start as f64 <= playback_time && (s.end == 0 || playback_time < s.end as f64))
+            .max_by_key(|(_, _, s)| s.start)
+            .map(|(i, j, _)| (i, j))
+    };
+    find(&matches_path).or_else(|| find(&matches_name))
+}
+
+/// The scene (video and scene index) of the file with the boundaries, wherever it is now (e.g. after scenes were removed or reordered).
+pub fn find_scene(instructional: &Instructional, file: &str, start: usize, end: usize) -> Option<(usize, usize)> {
+    instructional.videos.iter().enumerate()
+        .flat_map(|(i, v)| v.scenes.iter().enumerate().map(move |(j, s)| (i, j, s)))
+        .find(|(_, _, s)| s.file == file && s.start == start && s.end == end)
+        .map(|(i, j, _)| (i, j))
+}
+
+/// How much of the scene has been played (0.0 to 1.0). Scenes without an end last until the end of the video.
+pub fn scene_progress(scene: &Scene, playback_time: f64, duration: f64) -> f32 {
+    let end = if scene.end > scene.start { scene.end as f64 } else { duration };
+    if end <= scene.start as f64 {
+        return 0.0;
+    }
+    ((playback_time - scene.start as f64) / (end - scene.start as f64)).max(0.0).min(1.0) as f32
+}
+
 pub fn split_scene(index: usize, s: Scene) -> Option<Video>  {
    let cmd = if cfg!(target_os = "windows") { "ffmpeg.exe" } else { "ffmpeg" };
     let extension = &s.file.split(".").last().unwrap_or("mp4");
@@ -521,18 +707,20 @@ pub fn split_scene(index: usize, s: Scene) -> Option<Video>  {
                 .output()
                 .unwrap();
 
-           return Some(Video {index, file: file.to_string(), duration: 0, scenes: vec![Scene {index: 1, title: s.title.to_string(), file: file.to_string(), text, start: 0, end: 0, labels: vec![] }]});
+           return Some(Video {index, file: file.to_string(), duration: 0, scenes: vec![Scene {index: 1, title: s.title.to_string(), file: file.to_string(), text, start: 0, end: 0, labels: vec![], speed: s.speed }]});
 }
 
-pub fn play_scene(scene: Scene) {
+pub fn play_scene(socket: &str, scene: Scene, options: &PlaybackOptions) {
     let cmd = if cfg!(target_os = "windows") { "mpv.exe" } else { "mpv" };
     let path = escape_path(&scene.file);
+    let mut args = vec![format!("--input-ipc-server={}", socket)];
+    if let Some(input_conf) = write_mpv_input_conf() {
+        args.push(format!("--input-conf={}", input_conf.display()));
+    }
+    args.extend(mpv_range_args(scene.start, scene.end, options));
+    args.push(path.clone());
     let out = std::process::Command::new(cmd)
-        .args([
-            format!("--input-ipc-server=/tmp/mpv.sock"),
-            format!("--start={}", scene.start),
-            path.clone()
-        ])
+        .args(args)
         .stdin(Stdio::null())
         .output()
         .unwrap();
@@ -542,8 +730,8 @@ pub fn play_scene(scene: Scene) {
     }
 }
 
-pub fn get_mpv_playback_time() -> String {
-    let mpv = Mpv::connect("mpv.socket").unwrap();
+pub fn get_mpv_playback_time(socket: &str) -> String {
+    let mpv = Mpv::connect(socket).unwrap();
     let playback_time:String = mpv.get_property("playback-time").unwrap();
     return playback_time;
 }
@@ -559,63 +747,29 @@ pub fn ocr_preprocess_img(path: String, ocr_settings: &OcrSettings) -> Option<St
     return Some(output_filename);
 }
 
-pub fn ocr_preprocess(src_img: Mat, dst_img: &mut Mat, settings: &OcrSettings) {
-    let mut cur_img = Mat::default();
-    src_img.copy_to(&mut cur_img).expect("Failed to copy image!");
-    if settings.grayscale {
-        cvt_color(&cur_img, dst_img, COLOR_BGRA2GRAY, 0).expect("Failed to convert image to grayscale!");
-        dst_img.copy_to(&mut cur_img).expect("Failed to copy image!");
-    }
-
-    if settings.gaussian_thresholding {
-        adaptive_threshold(&cur_img, dst_img, settings.gaussian_thresholding_max_value, ADAPTIVE_THRESH_GAUSSIAN_C, THRESH_BINARY, settings.gaussian_thresholding_blocksize, settings.gaussian_thresholding_c).expect("Failed to apply Gaussian thresholding!");
-        dst_img.copy_to(&mut cur_img).expect("Failed to copy image!");
-    }
-
-    if settings.otsu_thresholding {
-        threshold(&cur_img, dst_img, settings.otsu_thresholding_min_value, settings.otsu_thresholding_max_value, THRESH_OTSU).expect("failed to apply OTSU thresholding!");
-        dst_img.copy_to(&mut cur_img).expect("Failed to copy image!");
-    }
-
-    if settings.invert {
-        bitwise_not(&mut cur_img, dst_img, &Mat::default()).expect("Failed to invert image!");
-        dst_img.copy_to(&mut cur_img).expect("Failed to copy image!");
-    }
-
-    if settings.denoise {
-        let mut strength_vector = opencv::core::Vector::new();
-        strength_vector.insert(0, settings.denoise_strength);
-        fast_nl_means_denoising_vec(&mut cur_img, dst_img, &strength_vector, 7, 21, NORM_L1);
-    }
-//    fast_nl_means_denoising(&mut cur_img, dst_img, 3.0, 3.0, 7, 21);
-    dst_img.copy_to(&mut cur_img).expect("Failed to copy image!");
+/// Writes the image of every preprocessing stage next to the source image.
+/// Returns the name of each stage along with the path to its image.
+pub fn ocr_preview_img(path: String, ocr_settings: &OcrSettings) -> Vec<(String, String)> {
+    let src_img = imread(path.as_str(), IMREAD_COLOR).expect("Failed to load image!");
+    ocr_preprocess_stages(src_img, &ocr_settings.steps).into_iter()
+        .enumerate()
+        .map(|(i, (name, img))| {
+            let stage_filename = path.replace(".png", format!("-ocr-stage-{}.png", i).as_str());
+            imwrite(&stage_filename, &img, &VectorOfi32::new()).expect("Failed to write preprocessing stage image!");
+            (name, stage_filename)
+        })
+        .collect()
+}
 
-    if settings.erode {
-        let anchor = opencv::core::Point::new(-1, -1);
-        let kernel = get_structuring_element(MORPH_RECT, Size_ { width: settings.erode_iterations, height: settings.erode_kernel_size }  , anchor).unwrap();
-        match erode(&mut cur_img, dst_img, &kernel, anchor, settings.erode_iterations, BORDER_CONSTANT, morphology_default_border_value().unwrap()) {
-            Ok(_) => {
-                dst_img.copy_to(&mut cur_img).expect("Failed to copy image!");
-            },
-            Err(e) => { println!("Erosion failed:{}", e) }
-            } 
-    }
-    if settings.dilate {
-        let anchor = opencv::core::Point::new(-1, -1);
-        let kernel = get_structuring_element(MORPH_RECT, Size_ { width: settings.erode_iterations, height: settings.erode_kernel_size }  , anchor).unwrap();
-        match dilate(&mut cur_img, dst_img, &kernel, anchor, settings.dilate_iterations, BORDER_CONSTANT, morphology_default_border_value().unwrap()) {
-            Ok(_) => {
-                dst_img.copy_to(&mut cur_img).expect("Failed to copy image!");
-            },
-            Err(e) => { println!("Erosion failed:{}", e) }
-        } 
-    }
+pub fn ocr_preprocess(src_img: Mat, dst_img: &mut Mat, settings: &OcrSettings) {
+    let (_, result) = ocr_preprocess_stages(src_img, &settings.steps).pop().expect("Failed to find original image!");
+    result.copy_to(dst_img).expect("Failed to copy image!");
 }
 
 pub fn scene_text_with_settings(creator: String, title: String, scene: &Scene, general_settings: &GeneralSettings, ocr_settings: &OcrSettings) -> Option<String> {
     if let Some(img_file) = scene_to_image(creator, title, scene) {
         if let Some(ocr_file) = ocr_preprocess_img(img_file, ocr_settings) {
-            let tesseract =  Tesseract::new_with_oem(None, Some("eng"), OcrEngineMode::LstmOnly).expect("Failed to initialize tesseract!");
+            let tesseract =  Tesseract::new_with_oem(None, Some(ocr_settings.language.as_str()), OcrEngineMode::LstmOnly).expect("Failed to initialize tesseract!");
             let mut recongnize = tesseract.set_image(ocr_file.as_str()).expect("Failed to set image!").recognize().expect("Failed to recognize text!");
             let alpha_re = Regex::new(r"[^a-zA-Z]+$").unwrap();
             let space_or_newline_re = Regex::new(r"[\n\r\s]+").unwrap();
@@ -927,6 +1081,77 @@ And another
         assert_eq!("Here's another comment\nAnd another", i.videos[1].scenes[0].text);
     }
 
+    #[test]
+    fn test_should_parse_provider() {
+        let i = parse_org(String::from("#+creator: iocanel\n#+title: my test\n#+provider: local\n"));
+        assert_eq!("local", i.provider);
+        let i = parse_org(String::from("#+creator: iocanel\n#+title: my test\n"));
+        assert_eq!(DEFAULT_PROVIDER, i.provider);
+    }
+
+    #[test]
+    fn test_should_find_playing_scene() {
+        let mut instructional = Instructional::new();
+        let scene = |file: &str, start: usize, end: usize| Scene { index: 0, title: format!("{} {}", file, start), file: file.to_string(), text: "".to_string(), labels: vec![], start, end, speed: 1.0 };
+        instructional.videos.push(Video { index: 0, file: "/videos/vol1.mp4".to_string(), duration: 600, scenes: vec![scene("/videos/vol1.mp4", 0, 60), scene("/videos/vol1.mp4", 60, 0)] });
+        instructional.videos.push(Video { index: 1, file: "/videos/vol2.mp4".to_string(), duration: 600, scenes: vec![scene("/videos/vol2.mp4", 0, 120)] });
+
+        assert_eq!(Some((0, 0)), find_playing_scene(&instructional, "/videos/vol1.mp4", 30.0));
+        assert_eq!(Some((0, 1)), find_playing_scene(&instructional, "/videos/vol1.mp4", 500.0));
+        assert_eq!(Some((1, 0)), find_playing_scene(&instructional, "vol2.mp4", 60.0));
+        assert_eq!(None, find_playing_scene(&instructional, "/videos/vol2.mp4", 130.0));
+        assert_eq!(None, find_playing_scene(&instructional, "/videos/vol3.mp4", 30.0));
+
+        assert_eq!(0.5, scene_progress(&instructional.videos[1].scenes[0], 60.0, 600.0));
+        assert_eq!(0.25, scene_progress(&instructional.videos[0].scenes[1], 195.0, 600.0));
+
+        assert_eq!(Some((0, 1)), find_scene(&instructional, "/videos/vol1.mp4", 60, 0));
+        instructional.videos[0].scenes.remove(0);
+        assert_eq!(Some((0, 0)), find_scene(&instructional, "/videos/vol1.mp4", 60, 0));
+        assert_eq!(None, find_scene(&instructional, "/videos/vol1.mp4", 0, 60));
+    }
+
+    #[test]
+    fn test_should_parse_scene_speed() {
+        let content = String::from("
+#+creator: iocanel
+#+title: my test
+
+*** Scene 1 :video:
+:PROPERTIES:
+:FILE_OR_URL: vol1.mp4
+:START_TIMESTAMP: 0
+:END_TIMESTAMP: 100
+:SPEED: 0.5
+:END:
+
+*** Scene 2 :video:
+:PROPERTIES:
+:FILE_OR_URL: vol1.mp4
+:START_TIMESTAMP: 100
+:END_TIMESTAMP: 200
+:END:
+");
+        let i = parse_org(content);
+        assert_eq!(2, i.videos[0].scenes.len());
+        assert_eq!(0.5, i.videos[0].scenes[0].speed);
+        assert!(i.videos[0].scenes[0].labels.is_empty());
+        assert_eq!(1.0, i.videos[0].scenes[1].speed);
+    }
+
+    #[test]
+    fn test_should_parse_metadata() {
+        let i = parse_org(String::from("#+creator: iocanel\n#+title: my test\n#+description: The back system\n#+published: 2021-03-01T00:00:00-05:00\n#+tags: gi, no-gi\n#+cover: https://cdn.example.com/files/back.jpg?v=123\n"));
+        assert_eq!("The back system", i.description);
+        assert_eq!("2021-03-01T00:00:00-05:00", i.published_at);
+        assert_eq!(vec!["gi".to_string(), "no-gi".to_string()], i.tags);
+        assert_eq!("https://cdn.example.com/files/back.jpg?v=123", i.cover);
+        assert_eq!("cover.jpg", cover_file_name(&i.cover));
+        assert!(cover_cache_name(&i.cover).ends_with("-back.jpg"));
+        assert_ne!(cover_cache_name("https://cdn.example.com/products/a/1.jpg"), cover_cache_name("https://cdn.example.com/products/b/1.jpg"));
+        assert!(parse_org(String::from("#+creator: iocanel\n")).tags.is_empty());
+    }
+
     #[test]
     fn test_time_to_seconds() {
         assert_eq!(0, time_to_seconds("0"));
@@ -0,0 +1,183 @@
+use std::fs::File;
+use std::io::prelude::*;
+use std::path::{Path, PathBuf};
+use itertools::Itertools;
+use scraper::Html;
+use scraper::Selector;
+use crate::Instructional;
+use crate::MetadataProvider;
+use crate::get_data_dir;
+use crate::rank_by_match;
+use crate::scrape_html;
+
+pub static LOCAL_PROVIDER: &str = "local";
+pub static LOCAL_PROVIDER_NAME: &str = "Saved pages";
+
+static PAGE_EXTENSIONS: &[&str] = &["html", "htm", "txt"];
+
+/// Reads products from pages saved on disk.
+/// Pages are either named `<creator> - <title>.html` or placed in a folder per creator: `<creator>/<title>.html`.
+/// Plain text files (`.txt`) are expected to contain the chapter list as is.
+pub struct LocalProvider {
+    dir: PathBuf,
+}
+
+impl LocalProvider {
+    pub fn new() -> LocalProvider {
+        LocalProvider { dir: get_local_pages_dir() }
+    }
+
+    pub fn with_dir(dir: PathBuf) -> LocalProvider {
+        LocalProvider { dir }
+    }
+
+    fn pages(&self) -> Vec<Instructional> {
+        let mut result: Vec<Instructional> = vec![];
+        let entries = match std::fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(_) => return result,
+        };
+        for entry in entries.filter_map(|e| e.ok()) {
+            let path = entry.path();
+            if path.is_dir() {
+                let creator = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+                if let Ok(files) = std::fs::read_dir(&path) {
+                    files.filter_map(|e| e.ok())
+                        .map(|e| e.path())
+                        .filter(|p| is_page(p))
+                        .for_each(|p| result.push(page_to_instructional(&p, Some(creator.to_string()))));
+                }
+            } else if is_page(&path) {
+                result.push(page_to_instructional(&path, None));
+            }
+        }
+        result.sort_by(|a, b| (&a.creator, &a.title).cmp(&(&b.creator, &b.title)));
+        result
+    }
+}
+
+impl MetadataProvider for LocalProvider {
+    fn id(&self) -> &'static str {
+        LOCAL_PROVIDER
+    }
+
+    fn name(&self) -> &'static str {
+        LOCAL_PROVIDER_NAME
+    }
+
+    fn popular_creators(&self) -> Vec<String> {
+        self.list_creators()
+    }
+
+    fn list_creators(&self) -> Vec<String> {
+        self.pages().into_iter()
+            .map(|i| i.creator)
+            .filter(|c| !c.is_empty())
+            .sorted()
+            .dedup()
+            .collect()
+    }
+
+    fn search_products(&self, creator: String, title: String) -> Result<Vec<Instructional>, String> {
+        Ok(rank_by_match(self.pages(), &creator, &title, |i| (i.creator.to_string(), i.title.to_string())))
+    }
+
+    fn fetch_chapters(&self, url: String) -> Result<String, String> {
+        let path = PathBuf::from(url.trim_start_matches("file://"));
+        let mut content = String::new();
+        File::open(&path)
+            .and_then(|mut f| f.read_to_string(&mut content))
+            .map_err(|e| format!("Failed to read saved page: {}: {}", path.display(), e))?;
+        if !is_html(&path) {
+            return Ok(content);
+        }
+        // Pages saved from bjjfanatics.com still have the chapter table, other stores are read as plain text.
+        let chapters = scrape_html(content.to_string());
+        if !chapters.is_empty() {
+            return Ok(chapters);
+        }
+        Ok(html_to_text(content))
+    }
+
+    fn refresh(&self) -> Result<(), String> {
+        // Nothing is cached, pages are read from disk on every request.
+        Ok(())
+    }
+}
+
+pub fn get_local_pages_dir() -> PathBuf {
+    get_data_dir().join("pages")
+}
+
+fn is_page(path: &Path) -> bool {
+    path.is_file() && path.extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .map(|e| PAGE_EXTENSIONS.contains(&e.as_str()))
+        .unwrap_or(false)
+}
+
+fn is_html(path: &Path) -> bool {
+    path.extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .map(|e| e == "html" || e == "htm")
+        .unwrap_or(false)
+}
+
+fn page_to_instructional(path: &Path, creator: Option<String>) -> Instructional {
+    let stem = path.file_stem().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    let (creator, title) = match creator {
+        Some(c) => (c, stem),
+        None => match stem.split_once(" - ") {
+            Some((c, t)) => (c.trim().to_string(), t.trim().to_string()),
+            None => ("".to_string(), stem),
+        },
+    };
+    let mut instructional = Instructional::new();
+    instructional.creator = creator;
+    instructional.title = title;
+    instructional.url = path.to_string_lossy().to_string();
+    instructional.provider = LOCAL_PROVIDER.to_string();
+    instructional
+}
+
+/// The visible text of the page body, one line per text node.
+fn html_to_text(body: String) -> String {
+    let html = Html::parse_document(&body);
+    let selector = &Selector::parse("body").expect("Error during the parsing using the given selector");
+    html.select(selector)
+        .flat_map(|el| el.text())
+        .map(|s| s.trim())
+        .filter(|s| !s.is_empty())
+        .collect::<Vec<&str>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_search_saved_pages() {
+        let dir = std::env::temp_dir().join("hg2jj-test-local-provider");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("Lachlan Giles")).unwrap();
+        std::fs::write(dir.join("John Danaher - Back Attacks.html"), "<html><body><section><table><tr><td>Intro</td><td>00:00:00 - 00:01:00</td></tr></table></section></body></html>").unwrap();
+        std::fs::write(dir.join("Lachlan Giles").join("Half Guard.txt"), "Intro\n00:00 - 01:00\n").unwrap();
+        std::fs::write(dir.join("notes.md"), "ignored").unwrap();
+
+        let provider = LocalProvider::with_dir(dir.clone());
+        assert_eq!(vec!["John Danaher".to_string(), "Lachlan Giles".to_string()], provider.list_creators());
+
+        let found = provider.search_products("John Danaher".to_string(), "".to_string()).unwrap();
+        assert_eq!(1, found.len());
+        assert_eq!("Back Attacks", found[0].title);
+        assert_eq!(Ok("Intro\n00:00:00 - 00:01:00".to_string()), provider.fetch_chapters(found[0].url.to_string()));
+
+        let found = provider.search_products("".to_string(), "Half".to_string()).unwrap();
+        assert_eq!(1, found.len());
+        assert_eq!("Lachlan Giles", found[0].creator);
+        assert_eq!(Ok("Intro\n00:00 - 01:00\n".to_string()), provider.fetch_chapters(found[0].url.to_string()));
+        assert!(provider.fetch_chapters(dir.join("missing.html").to_string_lossy().to_string()).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
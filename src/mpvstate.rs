@@ -1,170 +1,468 @@
-use std::sync::mpsc::{Sender, Receiver};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::{channel, Sender, Receiver};
+use std::net::Shutdown;
+use std::os::unix::net::UnixStream;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 use mpvipc::{Error, Event, Mpv, MpvDataType, Property};
 
 use crate::app::Command;
+use crate::Scene;
+use crate::play_scene;
+use crate::Player;
+use crate::PlayerEvent;
+use crate::parse_script_message;
+use crate::PlayerState;
+use crate::MAIN_PLAYER;
+use crate::MPV_BACKEND;
+use crate::drill;
+use crate::requires_drill;
+use crate::PlaybackMode;
+use crate::PlaybackOptions;
 
-#[derive(Debug, Clone)]
-pub struct MpvState {
-    pub running: bool,
-    pub pause: bool,
-    pub path: Option<String>,
-    pub playback_time: f64,
-    pub duration: f64
-}
-impl MpvState {
-    pub fn new() -> Self {
-        MpvState {
-            running: false,
-            pause: false,
-            path: None,
-            playback_time: 0.0,
-            duration: 0.0
-        }
+/// How many times to try connecting to a starting mpv.
+static MPV_STARTUP_ATTEMPTS: usize = 20;
+/// The delay before reconnecting to mpv, doubled after each failed attempt up to the max.
+static MPV_MIN_BACKOFF_MILLIS: u64 = 100;
+static MPV_MAX_BACKOFF_MILLIS: u64 = 2000;
+/// How long to wait for mpv to exit when stopping it.
+static MPV_STOP_TIMEOUT_MILLIS: u64 = 2000;
+
+/// The ipc socket of the player. When no socket is configured, a socket unique to this session is used,
+/// so that multiple instances of the app (or a user's own mpv) don't collide.
+pub fn mpv_socket_path(configured: &str, player: &str) -> String {
+    if configured.trim().is_empty() {
+        return std::env::temp_dir().join(format!("hg2jj-{}-{}.sock", std::process::id(), player)).to_string_lossy().to_string();
     }
+    if player == MAIN_PLAYER {
+        return configured.trim().to_string();
+    }
+    format!("{}.{}", configured.trim(), player)
 }
 
-pub struct MpvMsg {
-    pub state: MpvState,
+/// An mpv instance controlled over its own ipc socket.
+pub struct MpvPlayer {
+    pub name: String,
+    pub socket: String,
+    pub state: PlayerState,
+    // Incremented each time a file is opened, so that loops driven for previous files stop.
+    generation: Arc<AtomicUsize>,
+    // Started on first use and kept until the player is dropped.
+    connection: Option<MpvConnection>,
 }
-impl MpvMsg {
-    pub fn new() -> Self {
-        MpvMsg {
-            state: MpvState::new()
+
+impl MpvPlayer {
+    pub fn new(name: &str, socket: String) -> Self {
+        MpvPlayer { name: name.to_string(), socket, state: PlayerState::new(), generation: Arc::new(AtomicUsize::new(0)), connection: None }
+    }
+
+    fn connect(&mut self) {
+        if self.connection.is_none() {
+            self.connection = Some(MpvConnection::new(&self.socket));
         }
     }
-    pub fn for_state(state: MpvState) -> Self {
-        MpvMsg {
-            state
+}
+
+impl Player for MpvPlayer {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn backend(&self) -> &'static str {
+        MPV_BACKEND
+    }
+
+    fn open(&mut self, file: &str, start: usize, end: usize, options: &PlaybackOptions) {
+        let current = self.generation.fetch_add(1, Ordering::SeqCst) + 1;
+        self.state = PlayerState::new();
+        let options = options.for_range(start, end);
+        let socket = self.socket.to_string();
+        let generation = self.generation.clone();
+        let scene = Scene { index: 0, title: "".to_string(), file: file.to_string(), text: "".to_string(), labels: vec![], start, end, speed: 1.0 };
+        // Waiting for the previous mpv to stop may take a while, so it's done along with launching the new one.
+        std::thread::spawn(move || {
+            mpv_stop(&socket);
+            if requires_drill(&options) {
+                drill_mpv(&socket, start, end, options, generation, current);
+            }
+            play_scene(&socket, scene, &options);
+        });
+        self.connect();
+    }
+
+    fn pause(&self) {
+        let socket = self.socket.to_string();
+        std::thread::spawn(move || {
+            mpv_pause(&socket);
+        });
+    }
+
+    fn seek(&self, seconds: f64) {
+        let socket = self.socket.to_string();
+        std::thread::spawn(move || {
+            mpv_seek(&socket, seconds);
+        });
+    }
+
+    fn stop(&mut self) {
+        self.generation.fetch_add(1, Ordering::SeqCst);
+        let socket = self.socket.to_string();
+        std::thread::spawn(move || {
+            mpv_stop(&socket);
+        });
+        self.state = PlayerState::new();
+    }
+
+    fn close(&mut self) {
+        self.generation.fetch_add(1, Ordering::SeqCst);
+        mpv_stop(&self.socket);
+        self.state = PlayerState::new();
+    }
+
+    fn set_speed(&self, speed: f64) {
+        let socket = self.socket.to_string();
+        std::thread::spawn(move || {
+            mpv_set_speed(&socket, speed);
+        });
+    }
+
+    fn frame_step(&self, forward: bool) {
+        let socket = self.socket.to_string();
+        std::thread::spawn(move || {
+            mpv_command(&socket, if forward { "frame-step" } else { "frame-back-step" }, &[]);
+        });
+    }
+
+    fn rewind(&self, seconds: f64) {
+        let socket = self.socket.to_string();
+        std::thread::spawn(move || {
+            mpv_command(&socket, "seek", &[&format!("-{}", seconds), "relative"]);
+        });
+    }
+
+    /// The connection keeps reconnecting, so an mpv launched outside the app is picked up once it's listening on the socket.
+    fn attach(&mut self) {
+        if Path::new(&self.socket).exists() {
+            self.connect();
         }
     }
+
+    fn state(&self) -> &PlayerState {
+        &self.state
+    }
+
+    fn update(&mut self) -> Vec<PlayerEvent> {
+        let mut events = vec![];
+        let connection = match &self.connection {
+            Some(connection) => connection,
+            None => return events,
+        };
+        while let Ok(event) = connection.events.try_recv() {
+            if let MpvEvent::ClientMessage(args) = &event {
+                let action = parse_script_message(args);
+                if let (Some(action), Some(path)) = (action, self.state.path.clone()) {
+                    events.push(PlayerEvent { action, path, playback_time: self.state.playback_time });
+                }
+            }
+            apply_mpv_event(&mut self.state, &event);
+        }
+        events
+    }
 }
 
-pub fn mpv_pause() {
-    let mut mpv = Mpv::connect("/tmp/mpv.sock");
-    match &mut mpv {
-        Ok(m) => {
-            let paused: bool = m.get_property("pause").unwrap();
-            m.set_property("pause", !paused).expect("Error pausing");
+/// The mpv arguments that limit playback to the scene.
+pub fn mpv_range_args(start: usize, end: usize, options: &PlaybackOptions) -> Vec<String> {
+    let mut args = vec![format!("--start={}", start)];
+    if options.speed != 1.0 {
+        args.push(format!("--speed={}", options.speed));
+    }
+    match options.mode {
+        PlaybackMode::Continue => (),
+        PlaybackMode::SceneOnly => {
+            args.push(format!("--end={}", end));
+            args.push("--keep-open=yes".to_string());
+        },
+        PlaybackMode::Loop if requires_drill(options) => args.push("--keep-open=yes".to_string()),
+        PlaybackMode::Loop => {
+            args.push(format!("--ab-loop-a={}", start));
+            args.push(format!("--ab-loop-b={}", end));
         },
-        _ => ()
     }
+    args
 }
 
-pub fn mpv_play(path: String) {
-    let mut mpv = Mpv::connect("/tmp/mpv.sock");
-    match &mut mpv {
-        Ok(m) => {
-            m.set_property("path", path).expect("Error playing");
-        },
-        _ => ()
+/// Loops the scene over the ipc socket, once mpv is up. The loop stops once the generation moves past the current one.
+fn drill_mpv(socket: &str, start: usize, end: usize, options: PlaybackOptions, generation: Arc<AtomicUsize>, current: usize) {
+    let socket = socket.to_string();
+    std::thread::spawn(move || {
+        let mut mpv = None;
+        for _ in 0..MPV_STARTUP_ATTEMPTS {
+            std::thread::sleep(Duration::from_millis(500));
+            if generation.load(Ordering::SeqCst) != current {
+                return;
+            }
+            if let Ok(m) = Mpv::connect(&socket) {
+                mpv = Some(m);
+                break;
+            }
+        }
+        let mpv = match mpv {
+            Some(m) => std::cell::RefCell::new(m),
+            None => return,
+        };
+        drill(start, end, options, generation, current,
+              || mpv.borrow_mut().get_property::<f64>("playback-time").ok(),
+              |pause| { let _ = mpv.borrow_mut().set_property("pause", pause); },
+              |time| { let _ = mpv.borrow_mut().set_property("time-pos", time); });
+    });
+}
+
+pub fn mpv_pause(socket: &str) {
+    if let Ok(m) = Mpv::connect(socket) {
+        let paused: bool = m.get_property("pause").unwrap_or(false);
+        if let Err(e) = m.set_property("pause", !paused) {
+            println!("Failed to pause mpv. Error: {}", e);
+        }
+    }
+}
+
+pub fn mpv_seek(socket: &str, seconds: f64) {
+    if let Ok(m) = Mpv::connect(socket) {
+        if let Err(e) = m.set_property("time-pos", seconds) {
+            println!("Failed to seek mpv. Error: {}", e);
+        }
+    }
+}
+
+pub fn mpv_set_speed(socket: &str, speed: f64) {
+    if let Ok(m) = Mpv::connect(socket) {
+        if let Err(e) = m.set_property("speed", speed) {
+            println!("Failed to set mpv speed. Error: {}", e);
+        }
     }
 }
 
-pub fn mpv_stop(state: &mut MpvState) {
-    let mut running = true;
-    let mut stopping = false;
+pub fn mpv_command(socket: &str, command: &str, args: &[&str]) {
+    if let Ok(m) = Mpv::connect(socket) {
+        if let Err(e) = m.run_command_raw(command, args) {
+            println!("Failed to run mpv command: {}. Error: {}", command, e);
+        }
+    }
+}
+
+pub fn mpv_play(socket: &str, path: String) {
+    if let Ok(m) = Mpv::connect(socket) {
+        if let Err(e) = m.set_property("path", path) {
+            println!("Failed to play in mpv. Error: {}", e);
+        }
+    }
+}
+
+/// Stops mpv and waits (for a limited time) until it no longer accepts connections, so that the socket can be reused.
+pub fn mpv_stop(socket: &str) {
+    let m = match Mpv::connect(socket) {
+        Ok(m) => m,
+        Err(_) => return,
+    };
     println!("Stopping mpv.");
-    while running {
-        let mut mpv = Mpv::connect("/tmp/mpv.sock");
-        match &mut mpv {
-            Ok(m) => {
-                if !stopping {
-                    match m.stop() {
-                        _ => () //Just ignore
-                    }
-                    stopping = true
-                }
-            },
-            _ => { running = false; println!("Stopped.") },
+    let _ = m.stop();
+    drop(m);
+    let started = std::time::Instant::now();
+    while started.elapsed() < Duration::from_millis(MPV_STOP_TIMEOUT_MILLIS) {
+        if Mpv::connect(socket).is_err() {
+            println!("Stopped.");
+            return;
         }
+        std::thread::sleep(Duration::from_millis(MPV_MIN_BACKOFF_MILLIS));
+    }
+    println!("Timed out waiting for mpv to stop.");
+}
+
+/// What happened in mpv, as observed over the ipc socket.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MpvEvent {
+    Connected,
+    Disconnected,
+    Time(f64),
+    Pause(bool),
+    Path(Option<String>),
+    Duration(f64),
+    Chapter(Option<i64>),
+    EndFile,
+    // A `script-message` (e.g. sent by a key binding).
+    ClientMessage(Vec<String>),
+}
+
+/// The delay before the next attempt to connect.
+pub fn next_backoff(backoff: Duration) -> Duration {
+    (backoff * 2).max(Duration::from_millis(MPV_MIN_BACKOFF_MILLIS)).min(Duration::from_millis(MPV_MAX_BACKOFF_MILLIS))
+}
+
+pub fn apply_mpv_event(state: &mut PlayerState, event: &MpvEvent) {
+    match event {
+        MpvEvent::Connected => state.running = true,
+        MpvEvent::Disconnected => *state = PlayerState::new(),
+        MpvEvent::Time(time) => {
+            state.running = true;
+            state.playback_time = *time;
+        },
+        MpvEvent::Pause(pause) => state.pause = *pause,
+        MpvEvent::Path(path) => {
+            state.running = true;
+            state.path = path.clone();
+        },
+        MpvEvent::Duration(duration) => state.duration = *duration,
+        MpvEvent::Chapter(_) | MpvEvent::EndFile | MpvEvent::ClientMessage(_) => (),
     }
-    state.running = false;
 }
 
+/// A connection to mpv that is kept for as long as it's needed: it reconnects with backoff while mpv is not running,
+/// and turns what happens in mpv into a stream of events. The connection stops once dropped.
+pub struct MpvConnection {
+    pub events: Receiver<MpvEvent>,
+    shutdown: Arc<AtomicBool>,
+    // The stream of the connection while it waits for events. It's closed on drop, so that the wait ends even when mpv is idle.
+    listening: Arc<Mutex<Option<UnixStream>>>,
+}
 
-pub fn watch_mpv(state: &mut MpvState, sender: &mut Sender<MpvMsg>) {
-    if !state.running {
-        state.running = true;
-        let sender = sender.clone();
+impl MpvConnection {
+    pub fn new(socket: &str) -> Self {
+        let (sender, events) = channel();
+        let shutdown = Arc::new(AtomicBool::new(false));
+        let stopped = shutdown.clone();
+        let listening = Arc::new(Mutex::new(None));
+        let stream = listening.clone();
+        let socket = socket.to_string();
         std::thread::spawn(move || {
-            loop { 
-                //println!("Connecting to mpv.");
-                let mut mpv = Mpv::connect("/tmp/mpv.sock");
-                match &mut mpv {
-                    Ok(m) => {
-                        //println!("Connected!");
-                        let mut new_state = get_mpv_state(m);
-                        //println!("Initial path:{}", new_state.clone().path.unwrap_or("".to_string()));
-                        sender.send(MpvMsg::for_state(new_state.clone())).unwrap();
-                        observe(m);
-                        loop {
-                            let event = m.event_listen();
-                            match event {
-                                Ok(e) => {
-                                    match e {
-                                        Event::PropertyChange { id: _, property } => {
-                                            match property {
-                                                Property::Pause(value) => { new_state.pause = value;  /*  println!("Pause") */ },
-                                                Property::Path(Some(value)) => { new_state.path = Some(value.clone()); /*  println!("Set path:{}", value) */ },
-                                                Property::PlaybackTime(Some(value)) => new_state.playback_time = value,
-                                                Property::PlaybackTime(None) => new_state.playback_time = 0.0,
-                                                Property::Duration(Some(value)) => new_state.duration = value,
-                                                Property::Duration(None) => new_state.duration = 0.0,
-                                                _ => (),
-                                            }
-                                            let _ = sender.send(MpvMsg::for_state(new_state.clone()));
-                                        },
-                                        Event::Shutdown => (),
-                                        Event::EndFile => (),
-                                        _ => {
-                                            observe(m);
-                                        },
-                                    }
-                                },
-                                _ =>  {
-
-                                    println!("Failed to get event!");
-                                    let _ = sender.send(MpvMsg::new()); return },
+            let mut backoff = Duration::from_millis(0);
+            while !stopped.load(Ordering::SeqCst) {
+                match Mpv::connect(&socket) {
+                    Ok(mut m) => {
+                        backoff = Duration::from_millis(0);
+                        if let Err(e) = listen(&mut m, &sender, &stopped, &stream) {
+                            if !stopped.load(Ordering::SeqCst) {
+                                println!("Lost connection to mpv. Error: {}", e);
                             }
                         }
+                        *stream.lock().unwrap_or_else(|e| e.into_inner()) = None;
+                        if sender.send(MpvEvent::Disconnected).is_err() {
+                            return;
+                        }
                     },
-                    _ =>  { 
-                            println!("Connection failed!");
-                            let _ = sender.send(MpvMsg::new()); 
+                    Err(_) => {
+                        backoff = next_backoff(backoff);
+                        std::thread::sleep(backoff);
                     },
                 }
             }
         });
+        MpvConnection { events, shutdown, listening }
     }
 }
 
-pub fn observe(m: &mut Mpv) {
-    m.observe_property(1, "path").unwrap();
-    m.observe_property(2, "pause").unwrap();
-    m.observe_property(3, "playback-time").unwrap();
-    m.observe_property(4, "duration").unwrap();
-    m.observe_property(5, "metadata").unwrap();
+impl Drop for MpvConnection {
+    fn drop(&mut self) {
+        self.shutdown.store(true, Ordering::SeqCst);
+        if let Some(stream) = self.listening.lock().unwrap_or_else(|e| e.into_inner()).take() {
+            let _ = stream.shutdown(Shutdown::Both);
+        }
+    }
 }
 
-pub fn update_mpv_state(state: &mut MpvState, receiver: &mut Receiver<MpvMsg>) {
-    while let Ok(msg) = receiver.try_recv() {
-        let new_state = msg.state;
-        state.pause = new_state.pause;
-        state.running = new_state.running;
-        if state.path != new_state.path {
-            state.path = new_state.path;
-            state.running = true;
+/// Forwards the events of a connected mpv, until it disconnects or the connection is no longer needed.
+fn listen(m: &mut Mpv, sender: &Sender<MpvEvent>, stopped: &AtomicBool, listening: &Mutex<Option<UnixStream>>) -> Result<(), Error> {
+    observe(m)?;
+    let initial = vec![
+        MpvEvent::Connected,
+        MpvEvent::Path(m.get_property("path").ok()),
+        MpvEvent::Pause(m.get_property("pause").unwrap_or(false)),
+        MpvEvent::Time(m.get_property("playback-time").unwrap_or(0.0)),
+        MpvEvent::Duration(m.get_property("duration").unwrap_or(0.0)),
+    ];
+    for event in initial {
+        if sender.send(event).is_err() {
+            return Ok(());
         }
-        if state.playback_time != new_state.playback_time {
-            state.playback_time = new_state.playback_time;
-            state.running = true;
-            state.pause = false;
+    }
+    // Only waiting for events can be interrupted by closing the stream (mpvipc doesn't expect the stream to close during a command).
+    // The stream is made available before checking whether the connection was dropped, so that either the drop or the check sees it.
+    *listening.lock().unwrap_or_else(|e| e.into_inner()) = m.get_stream_ref().try_clone().ok();
+    while !stopped.load(Ordering::SeqCst) {
+        let event = match m.event_listen()? {
+            Event::PropertyChange { id: _, property } => match property {
+                Property::Path(path) => Some(MpvEvent::Path(path)),
+                Property::Pause(pause) => Some(MpvEvent::Pause(pause)),
+                Property::PlaybackTime(time) => Some(MpvEvent::Time(time.unwrap_or(0.0))),
+                Property::Duration(duration) => Some(MpvEvent::Duration(duration.unwrap_or(0.0))),
+                Property::Unknown { name, data } if name == "chapter" => Some(MpvEvent::Chapter(match data {
+                    MpvDataType::Usize(n) => Some(n as i64),
+                    MpvDataType::Double(n) => Some(n as i64),
+                    _ => None,
+                })),
+                _ => None,
+            },
+            Event::EndFile => Some(MpvEvent::EndFile),
+            Event::ClientMessage { args } => Some(MpvEvent::ClientMessage(args)),
+            Event::Shutdown => return Ok(()),
+            _ => None,
+        };
+        if let Some(event) = event {
+            if sender.send(event).is_err() {
+                return Ok(());
+            }
         }
-        state.duration = new_state.duration;
-        //println!("{} - {} - {} - {}", state.running, state.pause, state.playback_time, state.path.as_ref().unwrap_or(&"none".to_string()));
     }
+    Ok(())
+}
+
+pub fn observe(m: &mut Mpv) -> Result<(), Error> {
+    m.observe_property(1, "path")?;
+    m.observe_property(2, "pause")?;
+    m.observe_property(3, "playback-time")?;
+    m.observe_property(4, "duration")?;
+    m.observe_property(5, "chapter")?;
+    Ok(())
 }
 
-fn get_mpv_state(mpv: &mut Mpv) -> MpvState {
-    return MpvState { running: true, pause: mpv.get_property("pause").unwrap_or(false), path: mpv.get_property("path").ok(), playback_time: mpv.get_property("playback_time").unwrap_or(0.0), duration: mpv.get_property("duration").unwrap_or(0.0) };
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mpv_socket_path() {
+        let main = mpv_socket_path("", MAIN_PLAYER);
+        let preview = mpv_socket_path("", crate::PREVIEW_PLAYER);
+        assert!(main.contains(&std::process::id().to_string()));
+        assert_ne!(main, preview);
+        assert_eq!("/tmp/my-mpv.sock", mpv_socket_path(" /tmp/my-mpv.sock ", MAIN_PLAYER));
+        assert_eq!("/tmp/my-mpv.sock.preview", mpv_socket_path("/tmp/my-mpv.sock", crate::PREVIEW_PLAYER));
+    }
+    #[test]
+    fn test_mpv_events() {
+        assert_eq!(Duration::from_millis(100), next_backoff(Duration::from_millis(0)));
+        assert_eq!(Duration::from_millis(800), next_backoff(Duration::from_millis(400)));
+        assert_eq!(Duration::from_millis(2000), next_backoff(Duration::from_millis(2000)));
+
+        let mut state = PlayerState::new();
+        apply_mpv_event(&mut state, &MpvEvent::Path(Some("/videos/vol1.mp4".to_string())));
+        apply_mpv_event(&mut state, &MpvEvent::Time(61.5));
+        apply_mpv_event(&mut state, &MpvEvent::Pause(true));
+        assert!(state.running && state.pause);
+        assert_eq!((Some("/videos/vol1.mp4".to_string()), 61.5), (state.path.clone(), state.playback_time));
+        apply_mpv_event(&mut state, &MpvEvent::Disconnected);
+        assert!(!state.running);
+        assert_eq!(None, state.path);
+    }
+
+    #[test]
+    fn test_mpv_range_args() {
+        let options = PlaybackOptions { mode: PlaybackMode::SceneOnly, loop_count: 0, loop_pause_secs: 0, speed: 1.0 };
+        assert_eq!(vec!["--start=60", "--end=120", "--keep-open=yes"], mpv_range_args(60, 120, &options));
+        assert_eq!(vec!["--start=60", "--speed=0.5", "--end=120", "--keep-open=yes"], mpv_range_args(60, 120, &PlaybackOptions { speed: 0.5, ..options }));
+        let options = PlaybackOptions { mode: PlaybackMode::Loop, ..options };
+        assert_eq!(vec!["--start=60", "--ab-loop-a=60", "--ab-loop-b=120"], mpv_range_args(60, 120, &options));
+        let options = PlaybackOptions { loop_count: 3, ..options };
+        assert_eq!(vec!["--start=60", "--keep-open=yes"], mpv_range_args(60, 120, &options));
+    }
 }
@@ -0,0 +1,210 @@
+use opencv::core::{add_weighted, bitwise_not, bitwise_or, in_range, Rect, Scalar, Size, BORDER_CONSTANT, BORDER_DEFAULT, NORM_L1};
+use opencv::photo::fast_nl_means_denoising_vec;
+use opencv::{
+    imgproc::*,
+    prelude::*,
+};
+
+/// The colors that can be extracted with the `ColorKey` step.
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum KeyColor {
+    Yellow,
+    Red,
+    White,
+}
+
+/// A single operation of the OCR preprocessing pipeline.
+/// Steps are applied in order, each one working on the output of the previous one.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum OcrStep {
+    Grayscale,
+    GaussianThresholding { max_value: f64, blocksize: i32, c: f64 },
+    OtsuThresholding { min_value: f64, max_value: f64 },
+    Invert,
+    Denoise { strength: f32 },
+    Erode { kernel_size: i32, iterations: i32 },
+    Dilate { kernel_size: i32, iterations: i32 },
+    Resize { scale: f64 },
+    Clahe { clip_limit: f64, tile_size: i32 },
+    Sharpen { amount: f64 },
+    ColorKey { color: KeyColor, min_saturation: f64, min_value: f64 },
+    // Expressed as fractions of the image width / height.
+    Crop { x: f32, y: f32, width: f32, height: f32 },
+}
+
+impl OcrStep {
+    pub fn name(&self) -> &'static str {
+        match self {
+            OcrStep::Grayscale => "Grayscale",
+            OcrStep::GaussianThresholding { .. } => "Gaussian Thresholding",
+            OcrStep::OtsuThresholding { .. } => "Otsu Thresholding",
+            OcrStep::Invert => "Invert",
+            OcrStep::Denoise { .. } => "Denoise",
+            OcrStep::Erode { .. } => "Erode",
+            OcrStep::Dilate { .. } => "Dilate",
+            OcrStep::Resize { .. } => "Resize",
+            OcrStep::Clahe { .. } => "CLAHE Contrast",
+            OcrStep::Sharpen { .. } => "Sharpen",
+            OcrStep::ColorKey { .. } => "Color Key",
+            OcrStep::Crop { .. } => "Crop",
+        }
+    }
+
+    /// One instance of every step, using sensible default values.
+    pub fn all() -> Vec<OcrStep> {
+        vec![
+            OcrStep::Grayscale,
+            OcrStep::GaussianThresholding { max_value: 255.0, blocksize: 11, c: 2.0 },
+            OcrStep::OtsuThresholding { min_value: 0.0, max_value: 255.0 },
+            OcrStep::Invert,
+            OcrStep::Denoise { strength: 3.0 },
+            OcrStep::Erode { kernel_size: 3, iterations: 1 },
+            OcrStep::Dilate { kernel_size: 3, iterations: 1 },
+            OcrStep::Resize { scale: 2.0 },
+            OcrStep::Clahe { clip_limit: 2.0, tile_size: 8 },
+            OcrStep::Sharpen { amount: 1.0 },
+            OcrStep::ColorKey { color: KeyColor::Yellow, min_saturation: 100.0, min_value: 100.0 },
+            OcrStep::Crop { x: 0.0, y: 0.0, width: 1.0, height: 1.0 },
+        ]
+    }
+}
+
+/// The steps that reproduce the classic (fixed order) preprocessing.
+pub fn default_ocr_steps() -> Vec<OcrStep> {
+    vec![
+        OcrStep::Grayscale,
+        OcrStep::Invert,
+        OcrStep::Denoise { strength: 3.0 },
+        OcrStep::Erode { kernel_size: 3, iterations: 1 },
+        OcrStep::Dilate { kernel_size: 3, iterations: 1 },
+    ]
+}
+
+/// Applies the step to the source image and returns the result.
+pub fn apply_ocr_step(src: &Mat, step: &OcrStep) -> opencv::Result<Mat> {
+    let mut dst = Mat::default();
+    match step {
+        OcrStep::Grayscale => {
+            if src.channels() == 1 {
+                src.copy_to(&mut dst)?;
+            } else {
+                cvt_color(src, &mut dst, COLOR_BGR2GRAY, 0)?;
+            }
+        },
+        OcrStep::GaussianThresholding { max_value, blocksize, c } => {
+            let gray = to_grayscale(src)?;
+            // Block size needs to be odd and greater than 1.
+            let blocksize = if blocksize % 2 == 0 { blocksize + 1 } else { *blocksize }.max(3);
+            adaptive_threshold(&gray, &mut dst, *max_value, ADAPTIVE_THRESH_GAUSSIAN_C, THRESH_BINARY, blocksize, *c)?;
+        },
+        OcrStep::OtsuThresholding { min_value, max_value } => {
+            let gray = to_grayscale(src)?;
+            threshold(&gray, &mut dst, *min_value, *max_value, THRESH_BINARY | THRESH_OTSU)?;
+        },
+        OcrStep::Invert => {
+            bitwise_not(src, &mut dst, &Mat::default())?;
+        },
+        OcrStep::Denoise { strength } => {
+            let mut strength_vector = opencv::core::Vector::new();
+            strength_vector.push(*strength);
+            fast_nl_means_denoising_vec(src, &mut dst, &strength_vector, 7, 21, NORM_L1)?;
+        },
+        OcrStep::Erode { kernel_size, iterations } => {
+            let anchor = opencv::core::Point::new(-1, -1);
+            let kernel = get_structuring_element(MORPH_RECT, Size::new(*kernel_size, *kernel_size), anchor)?;
+            erode(src, &mut dst, &kernel, anchor, *iterations, BORDER_CONSTANT, morphology_default_border_value()?)?;
+        },
+        OcrStep::Dilate { kernel_size, iterations } => {
+            let anchor = opencv::core::Point::new(-1, -1);
+            let kernel = get_structuring_element(MORPH_RECT, Size::new(*kernel_size, *kernel_size), anchor)?;
+            dilate(src, &mut dst, &kernel, anchor, *iterations, BORDER_CONSTANT, morphology_default_border_value()?)?;
+        },
+        OcrStep::Resize { scale } => {
+            let interpolation = if *scale >= 1.0 { INTER_CUBIC } else { INTER_AREA };
+            resize(src, &mut dst, Size::default(), *scale, *scale, interpolation)?;
+        },
+        OcrStep::Clahe { clip_limit, tile_size } => {
+            let gray = to_grayscale(src)?;
+            let mut clahe = create_clahe(*clip_limit, Size::new(*tile_size, *tile_size))?;
+            clahe.apply(&gray, &mut dst)?;
+        },
+        OcrStep::Sharpen { amount } => {
+            // Unsharp masking: subtract a blurred version of the image from the image itself.
+            let mut blurred = Mat::default();
+            gaussian_blur(src, &mut blurred, Size::default(), 3.0, 0.0, BORDER_DEFAULT)?;
+            add_weighted(src, 1.0 + amount, &blurred, -amount, 0.0, &mut dst, -1)?;
+        },
+        OcrStep::ColorKey { color, min_saturation, min_value } => {
+            if src.channels() != 3 {
+                println!("Color key requires a color image. Make sure it's applied before any grayscale conversion.");
+                src.copy_to(&mut dst)?;
+                return Ok(dst);
+            }
+            let mut hsv = Mat::default();
+            cvt_color(src, &mut hsv, COLOR_BGR2HSV, 0)?;
+            // OpenCV hue ranges from 0 to 180.
+            match color {
+                KeyColor::Yellow => {
+                    in_range(&hsv, &Scalar::new(20.0, *min_saturation, *min_value, 0.0), &Scalar::new(40.0, 255.0, 255.0, 0.0), &mut dst)?;
+                },
+                KeyColor::Red => {
+                    let mut low = Mat::default();
+                    let mut high = Mat::default();
+                    in_range(&hsv, &Scalar::new(0.0, *min_saturation, *min_value, 0.0), &Scalar::new(10.0, 255.0, 255.0, 0.0), &mut low)?;
+                    in_range(&hsv, &Scalar::new(170.0, *min_saturation, *min_value, 0.0), &Scalar::new(180.0, 255.0, 255.0, 0.0), &mut high)?;
+                    bitwise_or(&low, &high, &mut dst, &Mat::default())?;
+                },
+                KeyColor::White => {
+                    // White has (almost) no saturation, so the threshold is applied to the value only.
+                    in_range(&hsv, &Scalar::new(0.0, 0.0, *min_value, 0.0), &Scalar::new(180.0, 60.0, 255.0, 0.0), &mut dst)?;
+                },
+            }
+        },
+        OcrStep::Crop { x, y, width, height } => {
+            dst = crop(src, *x, *y, *width, *height)?;
+        },
+    }
+    Ok(dst)
+}
+
+/// Applies all the steps to the image and returns every intermediate image (including the original) along with a label.
+pub fn ocr_preprocess_stages(src_img: Mat, steps: &Vec<OcrStep>) -> Vec<(String, Mat)> {
+    let mut original = Mat::default();
+    src_img.copy_to(&mut original).expect("Failed to copy image!");
+    let mut stages: Vec<(String, Mat)> = vec![("Original".to_string(), original)];
+    let mut cur_img = src_img;
+    for step in steps {
+        match apply_ocr_step(&cur_img, step) {
+            Ok(img) => {
+                let mut stage_img = Mat::default();
+                img.copy_to(&mut stage_img).expect("Failed to copy image!");
+                stages.push((step.name().to_string(), stage_img));
+                cur_img = img;
+            },
+            Err(e) => println!("{} failed:{}", step.name(), e),
+        }
+    }
+    stages
+}
+
+/// Crops the image to the given region, expressed as fractions of the image width / height.
+pub fn crop(src: &Mat, x: f32, y: f32, width: f32, height: f32) -> opencv::Result<Mat> {
+    let cols = src.cols() as f32;
+    let rows = src.rows() as f32;
+    let left = (x.clamp(0.0, 1.0) * cols) as i32;
+    let top = (y.clamp(0.0, 1.0) * rows) as i32;
+    let w = ((width.clamp(0.0, 1.0) * cols) as i32).min(src.cols() - left);
+    let h = ((height.clamp(0.0, 1.0) * rows) as i32).min(src.rows() - top);
+    let mut dst = Mat::default();
+    if w <= 0 || h <= 0 {
+        println!("Ignoring empty crop region.");
+        src.copy_to(&mut dst)?;
+        return Ok(dst);
+    }
+    Mat::roi(src, Rect::new(left, top, w, h))?.copy_to(&mut dst)?;
+    Ok(dst)
+}
+
+fn to_grayscale(src: &Mat) -> opencv::Result<Mat> {
+    apply_ocr_step(src, &OcrStep::Grayscale)
+}
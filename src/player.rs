@@ -0,0 +1,329 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::Receiver;
+use std::sync::Arc;
+use std::time::Duration;
+use crate::MpvPlayer;
+use crate::VlcPlayer;
+use crate::mpv_socket_path;
+use crate::vlc_rc_address;
+
+/// The player used to watch scenes.
+pub static MAIN_PLAYER: &str = "main";
+/// A secondary player, e.g. for previewing a scene without interrupting the main player.
+pub static PREVIEW_PLAYER: &str = "preview";
+
+/// How often the playback time is checked while drilling a scene.
+static DRILL_POLL_INTERVAL_MILLIS: u64 = 200;
+
+pub static MPV_BACKEND: &str = "mpv";
+pub static VLC_BACKEND: &str = "vlc";
+
+#[derive(Debug, Clone)]
+pub struct PlayerState {
+    pub running: bool,
+    pub pause: bool,
+    pub path: Option<String>,
+    pub playback_time: f64,
+    pub duration: f64
+}
+impl PlayerState {
+    pub fn new() -> Self {
+        PlayerState {
+            running: false,
+            pause: false,
+            path: None,
+            playback_time: 0.0,
+            duration: 0.0
+        }
+    }
+}
+
+/// An action requested from inside the player (e.g. with a key binding), while watching fullscreen.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PlayerAction {
+    Bookmark,
+    SceneStart,
+    SceneEnd,
+    Note(String),
+}
+
+/// An action requested from inside the player, along with the file and the time it refers to.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlayerEvent {
+    pub action: PlayerAction,
+    pub path: String,
+    pub playback_time: f64,
+}
+
+pub struct PlayerMsg {
+    pub state: PlayerState,
+}
+impl PlayerMsg {
+    pub fn new() -> Self {
+        PlayerMsg {
+            state: PlayerState::new()
+        }
+    }
+    pub fn for_state(state: PlayerState) -> Self {
+        PlayerMsg {
+            state
+        }
+    }
+}
+
+/// What happens when playback reaches the end of the scene.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PlaybackMode {
+    // Keep playing the rest of the video.
+    Continue,
+    // Pause at the end of the scene.
+    SceneOnly,
+    // Play the scene again (A-B loop).
+    Loop,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PlaybackOptions {
+    pub mode: PlaybackMode,
+    // The number of times to play the scene when looping (0 loops forever).
+    pub loop_count: usize,
+    // Seconds to pause between repetitions.
+    pub loop_pause_secs: usize,
+    // The playback speed (1.0 is normal speed).
+    pub speed: f64,
+}
+
+impl PlaybackOptions {
+    pub fn new() -> Self {
+        PlaybackOptions { mode: PlaybackMode::Continue, loop_count: 0, loop_pause_secs: 0, speed: 1.0 }
+    }
+
+    /// The options that actually apply to the range, as scenes without a known end can't be played on their own.
+    pub fn for_range(&self, start: usize, end: usize) -> PlaybackOptions {
+        if end <= start {
+            return PlaybackOptions { mode: PlaybackMode::Continue, ..*self };
+        }
+        *self
+    }
+}
+
+impl Default for PlaybackOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DrillAction {
+    // Keep playing.
+    Play,
+    // Pause (for the configured time) and start the scene over.
+    Restart,
+    // Pause and stop drilling.
+    Finish,
+}
+
+/// Endless loops without pauses are handled by the players themselves, anything else is driven by the app.
+pub fn requires_drill(options: &PlaybackOptions) -> bool {
+    options.mode == PlaybackMode::Loop && (options.loop_count > 0 || options.loop_pause_secs > 0)
+}
+
+/// What a player that doesn't support pausing between repetitions should do, given the playback time and the repetitions played so far.
+pub fn next_drill_action(playback_time: f64, end: usize, played: usize, options: &PlaybackOptions) -> DrillAction {
+    if playback_time < end as f64 {
+        return DrillAction::Play;
+    }
+    match options.mode {
+        PlaybackMode::Loop if options.loop_count == 0 || played + 1 < options.loop_count => DrillAction::Restart,
+        PlaybackMode::Continue => DrillAction::Play,
+        _ => DrillAction::Finish,
+    }
+}
+
+/// Plays the scene as many times as the options specify, pausing between repetitions.
+/// Returns when done, when the player is no longer reachable (the time can't be read) or when the generation is no longer the current one (another file was opened).
+pub fn drill(start: usize, end: usize, options: PlaybackOptions, generation: Arc<AtomicUsize>, current: usize,
+             mut get_time: impl FnMut() -> Option<f64>, mut set_pause: impl FnMut(bool), mut seek: impl FnMut(f64)) {
+    let mut played = 0;
+    while generation.load(Ordering::SeqCst) == current {
+        std::thread::sleep(Duration::from_millis(DRILL_POLL_INTERVAL_MILLIS));
+        let time = match get_time() {
+            Some(time) => time,
+            None => return,
+        };
+        match next_drill_action(time, end, played, &options) {
+            DrillAction::Play => (),
+            DrillAction::Restart => {
+                played += 1;
+                set_pause(true);
+                std::thread::sleep(Duration::from_secs(options.loop_pause_secs as u64));
+                if generation.load(Ordering::SeqCst) != current {
+                    return;
+                }
+                seek(start as f64);
+                set_pause(false);
+            },
+            DrillAction::Finish => {
+                set_pause(true);
+                return;
+            },
+        }
+    }
+}
+
+/// A media player controlled by the app.
+pub trait Player {
+    /// The name of the player in the registry (e.g. `main`).
+    fn name(&self) -> &str;
+    /// The backend of the player (e.g. `mpv` or `vlc`).
+    fn backend(&self) -> &'static str;
+    /// Opens the file at the specified time (in seconds). Depending on the options, playback stops or loops at the end (0 for the end of the file).
+    fn open(&mut self, file: &str, start: usize, end: usize, options: &PlaybackOptions);
+    /// Toggles pause.
+    fn pause(&self);
+    /// Jumps to the specified time (in seconds).
+    fn seek(&self, seconds: f64);
+    fn stop(&mut self);
+    /// Stops the player and waits for it to exit, e.g. when the app exits.
+    fn close(&mut self);
+    /// Sets the playback speed (1.0 is normal speed).
+    fn set_speed(&self, speed: f64);
+    /// Pauses and moves one frame forward (or backward).
+    fn frame_step(&self, forward: bool);
+    /// Jumps the specified number of seconds back.
+    fn rewind(&self, seconds: f64);
+    /// Starts following a player that was launched outside the app, on the same socket / address.
+    fn attach(&mut self);
+    /// The last observed playback state (time, path, duration etc).
+    fn state(&self) -> &PlayerState;
+    /// Applies the state changes reported by the player and returns the actions requested from inside it.
+    fn update(&mut self) -> Vec<PlayerEvent>;
+    /// Whether the player is currently playing the file.
+    fn is_playing(&self, file: &str) -> bool {
+        self.state().path.as_ref().map(|p| p == file).unwrap_or(false)
+    }
+}
+
+/// The speeds the speed controls step through.
+static SPEEDS: [f64; 8] = [0.25, 0.5, 0.75, 1.0, 1.25, 1.5, 1.75, 2.0];
+
+/// The next speed up (or down), staying within the supported speeds.
+pub fn next_speed(speed: f64, faster: bool) -> f64 {
+    if faster {
+        SPEEDS.iter().find(|s| **s > speed + f64::EPSILON).copied().unwrap_or(SPEEDS[SPEEDS.len() - 1])
+    } else {
+        SPEEDS.iter().rev().find(|s| **s < speed - f64::EPSILON).copied().unwrap_or(SPEEDS[0])
+    }
+}
+
+pub fn get_player_backends() -> Vec<&'static str> {
+    vec![MPV_BACKEND, VLC_BACKEND]
+}
+
+/// Creates a player of the specified backend, falling back to mpv for unknown backends.
+pub fn create_player(backend: &str, name: &str, configured_address: &str) -> Box<dyn Player> {
+    match backend {
+        backend if backend == VLC_BACKEND => Box::new(VlcPlayer::new(name, vlc_rc_address(configured_address, name))),
+        _ => Box::new(MpvPlayer::new(name, mpv_socket_path(configured_address, name))),
+    }
+}
+
+/// Keeps track of the players (e.g. the main and the preview player), each with its own connection and state.
+pub struct PlayerRegistry {
+    backend: String,
+    configured_address: String,
+    players: Vec<Box<dyn Player>>,
+}
+
+impl PlayerRegistry {
+    pub fn new(backend: &str, configured_address: &str) -> Self {
+        PlayerRegistry { backend: backend.to_string(), configured_address: configured_address.to_string(), players: vec![] }
+    }
+
+    /// The player with the specified name, created on first use.
+    pub fn player(&mut self, name: &str) -> &mut Box<dyn Player> {
+        // Players of a previous backend are replaced once they stop.
+        let backend = self.backend.to_string();
+        self.players.retain(|p| p.name() != name || p.backend() == backend || p.state().running);
+        match self.players.iter().position(|p| p.name() == name) {
+            Some(index) => &mut self.players[index],
+            None => {
+                self.players.push(create_player(&self.backend, name, &self.configured_address));
+                self.players.last_mut().unwrap()
+            }
+        }
+    }
+
+    pub fn main(&mut self) -> &mut Box<dyn Player> {
+        self.player(MAIN_PLAYER)
+    }
+
+    pub fn preview(&mut self) -> &mut Box<dyn Player> {
+        self.player(PREVIEW_PLAYER)
+    }
+
+    /// Changes the backend and the configured socket / address. Players that are running are kept until they are stopped.
+    pub fn configure(&mut self, backend: &str, configured_address: &str) {
+        self.backend = backend.to_string();
+        self.configured_address = configured_address.to_string();
+        self.players.retain(|p| p.state().running);
+    }
+
+    pub fn update(&mut self) -> Vec<PlayerEvent> {
+        self.players.iter_mut().flat_map(|p| p.update()).collect()
+    }
+
+    /// Closes all the players, so that they don't outlive the app.
+    pub fn stop_all(&mut self) {
+        self.players.iter_mut().for_each(|p| p.close());
+    }
+}
+
+/// Applies the states reported by the player. Each message carries the complete state (an empty one when the player exited).
+pub fn update_player_state(state: &mut PlayerState, receiver: &mut Receiver<PlayerMsg>) {
+    while let Ok(msg) = receiver.try_recv() {
+        *state = msg.state;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_next_drill_action() {
+        let options = PlaybackOptions { mode: PlaybackMode::Loop, loop_count: 2, loop_pause_secs: 3, speed: 1.0 };
+        assert_eq!(DrillAction::Play, next_drill_action(59.5, 60, 0, &options));
+        assert_eq!(DrillAction::Restart, next_drill_action(60.0, 60, 0, &options));
+        assert_eq!(DrillAction::Finish, next_drill_action(60.0, 60, 1, &options));
+        assert_eq!(DrillAction::Restart, next_drill_action(61.0, 60, 100, &PlaybackOptions { loop_count: 0, ..options }));
+        assert_eq!(DrillAction::Finish, next_drill_action(60.0, 60, 0, &PlaybackOptions { mode: PlaybackMode::SceneOnly, ..options }));
+        assert_eq!(PlaybackMode::Continue, options.for_range(60, 0).mode);
+    }
+
+    #[test]
+    fn test_update_player_state() {
+        let (sender, mut receiver) = std::sync::mpsc::channel();
+        let mut state = PlayerState::new();
+        sender.send(PlayerMsg::for_state(PlayerState { running: true, pause: false, path: Some("vol1.mp4".to_string()), playback_time: 60.0, duration: 600.0 })).unwrap();
+        update_player_state(&mut state, &mut receiver);
+        assert!(state.running);
+        assert_eq!(Some("vol1.mp4".to_string()), state.path);
+
+        // The player exited.
+        sender.send(PlayerMsg::new()).unwrap();
+        update_player_state(&mut state, &mut receiver);
+        assert!(!state.running);
+        assert_eq!(None, state.path);
+    }
+
+    #[test]
+    fn test_next_speed() {
+        assert_eq!(1.25, next_speed(1.0, true));
+        assert_eq!(0.75, next_speed(1.0, false));
+        assert_eq!(2.0, next_speed(2.0, true));
+        assert_eq!(0.25, next_speed(0.25, false));
+        // Speeds that were set elsewhere snap to the supported ones.
+        assert_eq!(0.5, next_speed(0.6, false));
+    }
+}
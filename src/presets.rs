@@ -0,0 +1,255 @@
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
+use std::path::PathBuf;
+use crate::OcrSettings;
+use crate::OcrStep;
+use crate::get_data_dir;
+
+/// A named set of OCR settings, optionally associated with the creators it works best for.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct OcrPreset {
+    pub name: String,
+    #[serde(default)]
+    pub creators: Vec<String>,
+    pub settings: OcrSettings,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+struct OcrPresets {
+    presets: Vec<OcrPreset>,
+}
+
+/// The OCR settings as saved. Settings saved before the preprocessing steps were introduced have a flag (and parameters) per step instead.
+/// The region of interest used to be separate from the steps and is now migrated to a `Crop` step.
+#[derive(serde::Deserialize)]
+#[serde(default)]
+pub struct SavedOcrSettings {
+    steps: Option<Vec<OcrStep>>,
+    spellcheking: bool,
+    roi: bool,
+    roi_x: f32,
+    roi_y: f32,
+    roi_width: f32,
+    roi_height: f32,
+    language: String,
+
+    // The fixed order preprocessing.
+    grayscale: bool,
+    gaussian_thresholding: bool,
+    gaussian_thresholding_max_value: f64,
+    gaussian_thresholding_blocksize: i32,
+    gaussian_thresholding_c: f64,
+    otsu_thresholding: bool,
+    otsu_thresholding_min_value: f64,
+    otsu_thresholding_max_value: f64,
+    invert: bool,
+    denoise: bool,
+    denoise_strength: f32,
+    erode: bool,
+    erode_kernel_size: i32,
+    erode_iterations: i32,
+    dilate: bool,
+    dilate_kernel_size: i32,
+    dilate_iterations: i32,
+}
+
+impl Default for SavedOcrSettings {
+    fn default() -> Self {
+        let settings = OcrSettings::new();
+        SavedOcrSettings {
+            steps: None,
+            spellcheking: settings.spellcheking,
+            roi: false,
+            roi_x: 0.0,
+            roi_y: 0.0,
+            roi_width: 1.0,
+            roi_height: 1.0,
+            language: settings.language,
+            grayscale: true,
+            gaussian_thresholding: false,
+            gaussian_thresholding_max_value: 255.0,
+            gaussian_thresholding_blocksize: 11,
+            gaussian_thresholding_c: 2.0,
+            otsu_thresholding: false,
+            otsu_thresholding_min_value: 0.0,
+            otsu_thresholding_max_value: 255.0,
+            invert: true,
+            denoise: true,
+            denoise_strength: 3.0,
+            erode: true,
+            erode_kernel_size: 3,
+            erode_iterations: 1,
+            dilate: true,
+            dilate_kernel_size: 3,
+            dilate_iterations: 1,
+        }
+    }
+}
+
+impl From<SavedOcrSettings> for OcrSettings {
+    fn from(saved: SavedOcrSettings) -> Self {
+        let mut steps = match saved.steps {
+            Some(ref steps) => steps.to_vec(),
+            None => legacy_ocr_steps(&saved),
+        };
+        // The region of interest was cropped before any other preprocessing.
+        if saved.roi {
+            steps.insert(0, OcrStep::Crop { x: saved.roi_x, y: saved.roi_y, width: saved.roi_width, height: saved.roi_height });
+        }
+        OcrSettings {
+            steps,
+            spellcheking: saved.spellcheking,
+            language: saved.language,
+        }
+    }
+}
+
+/// The steps equivalent to the fixed order preprocessing, in the order it was applied.
+fn legacy_ocr_steps(saved: &SavedOcrSettings) -> Vec<OcrStep> {
+    vec![
+        Some(OcrStep::Grayscale).filter(|_| saved.grayscale),
+        Some(OcrStep::GaussianThresholding { max_value: saved.gaussian_thresholding_max_value, blocksize: saved.gaussian_thresholding_blocksize, c: saved.gaussian_thresholding_c }).filter(|_| saved.gaussian_thresholding),
+        Some(OcrStep::OtsuThresholding { min_value: saved.otsu_thresholding_min_value, max_value: saved.otsu_thresholding_max_value }).filter(|_| saved.otsu_thresholding),
+        Some(OcrStep::Invert).filter(|_| saved.invert),
+        Some(OcrStep::Denoise { strength: saved.denoise_strength }).filter(|_| saved.denoise),
+        Some(OcrStep::Erode { kernel_size: saved.erode_kernel_size, iterations: saved.erode_iterations }).filter(|_| saved.erode),
+        Some(OcrStep::Dilate { kernel_size: saved.dilate_kernel_size, iterations: saved.dilate_iterations }).filter(|_| saved.dilate),
+    ].into_iter().flatten().collect()
+}
+
+pub fn get_ocr_presets_file() -> PathBuf {
+    get_data_dir().join("ocr-presets.json")
+}
+
+/// The presets that ship with the application.
+/// They are used until the user saves presets of their own.
+pub fn default_ocr_presets() -> Vec<OcrPreset> {
+    let default = OcrSettings::new();
+
+    // White text on black slates.
+    let mut slates = OcrSettings::new();
+    slates.steps = vec![
+        OcrStep::Grayscale,
+        OcrStep::OtsuThresholding { min_value: 0.0, max_value: 255.0 },
+        OcrStep::Invert,
+        OcrStep::Denoise { strength: 3.0 },
+    ];
+
+    // Titles overlayed on top of the actual footage.
+    let mut overlays = OcrSettings::new();
+    overlays.steps = vec![
+        OcrStep::Grayscale,
+        OcrStep::GaussianThresholding { max_value: 255.0, blocksize: 31, c: 10.0 },
+        OcrStep::Invert,
+        OcrStep::Denoise { strength: 10.0 },
+        OcrStep::Erode { kernel_size: 3, iterations: 1 },
+        OcrStep::Dilate { kernel_size: 3, iterations: 1 },
+    ];
+
+    // Titles in the bottom third of the frame.
+    let mut lower_thirds = OcrSettings::new();
+    lower_thirds.steps = vec![
+        OcrStep::Crop { x: 0.0, y: 0.66, width: 1.0, height: 0.34 },
+        OcrStep::Resize { scale: 2.0 },
+        OcrStep::Grayscale,
+        OcrStep::OtsuThresholding { min_value: 0.0, max_value: 255.0 },
+        OcrStep::Invert,
+        OcrStep::Denoise { strength: 3.0 },
+    ];
+
+    vec![
+        OcrPreset { name: "Default".to_string(), creators: vec![], settings: default },
+        OcrPreset { name: "Slates".to_string(), creators: vec!["John Danaher".to_string()], settings: slates },
+        OcrPreset { name: "Overlays".to_string(), creators: vec!["Gordon Ryan".to_string()], settings: overlays },
+        OcrPreset { name: "Lower thirds".to_string(), creators: vec!["Lachlan Giles".to_string()], settings: lower_thirds },
+    ]
+}
+
+pub fn load_ocr_presets() -> Vec<OcrPreset> {
+    let path = get_ocr_presets_file();
+    if !path.exists() {
+        return default_ocr_presets();
+    }
+    let file = File::open(&path).expect("Failed to open OCR presets file!");
+    let reader = BufReader::new(file);
+    match serde_json::from_reader::<_, OcrPresets>(reader) {
+        Ok(data) => data.presets,
+        Err(e) => {
+            println!("Failed to read OCR presets from: {}. Using defaults. Error: {}", path.display(), e);
+            default_ocr_presets()
+        }
+    }
+}
+
+pub fn save_ocr_presets(presets: &Vec<OcrPreset>) {
+    let path = get_ocr_presets_file();
+    std::fs::create_dir_all(path.parent().expect("Failed to find data dir!")).expect("Failed to create data dir!");
+    let data = OcrPresets { presets: presets.to_vec() };
+    let file = File::create(path).expect("Failed to create OCR presets file!");
+    let mut out = BufWriter::new(file);
+    out.write_all(serde_json::to_string_pretty(&data).expect("Failed to serialize OCR presets!").as_bytes()).expect("Failed to write OCR presets!");
+}
+
+/// Adds the preset or replaces an existing preset with the same name.
+pub fn upsert_ocr_preset(presets: &mut Vec<OcrPreset>, preset: OcrPreset) {
+    match presets.iter().position(|p| p.name == preset.name) {
+        Some(i) => presets[i] = preset,
+        None => presets.push(preset),
+    }
+}
+
+pub fn find_ocr_preset_for_creator(presets: &Vec<OcrPreset>, creator: &str) -> Option<OcrPreset> {
+    let creator = creator.trim().to_lowercase();
+    if creator.is_empty() {
+        return None;
+    }
+    presets.iter()
+        .find(|p| p.creators.iter().any(|c| c.trim().to_lowercase() == creator))
+        .cloned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_preset_for_creator() {
+        let presets = default_ocr_presets();
+        assert_eq!("Slates", find_ocr_preset_for_creator(&presets, "John Danaher").unwrap().name);
+        assert_eq!("Slates", find_ocr_preset_for_creator(&presets, " john danaher ").unwrap().name);
+        assert!(find_ocr_preset_for_creator(&presets, "Unknown").is_none());
+        assert!(find_ocr_preset_for_creator(&presets, "").is_none());
+    }
+
+    #[test]
+    fn test_migrate_legacy_settings() {
+        let legacy: OcrSettings = serde_json::from_str(r#"{"grayscale": true, "otsu_thresholding": true, "otsu_thresholding_min_value": 10.0, "otsu_thresholding_max_value": 255.0,
+            "invert": false, "denoise": true, "denoise_strength": 5.0, "erode": false, "dilate": false, "spellcheking": false, "roi": true, "roi_y": 0.5}"#).unwrap();
+        assert_eq!(vec![OcrStep::Crop { x: 0.0, y: 0.5, width: 1.0, height: 1.0 }, OcrStep::Grayscale, OcrStep::OtsuThresholding { min_value: 10.0, max_value: 255.0 }, OcrStep::Denoise { strength: 5.0 }], legacy.steps);
+        assert!(!legacy.spellcheking);
+
+        // Settings saved along with the steps, but with a separate region of interest.
+        let separate: OcrSettings = serde_json::from_str(r#"{"steps": ["Grayscale"], "roi": true, "roi_y": 0.66, "roi_height": 0.34}"#).unwrap();
+        assert_eq!(vec![OcrStep::Crop { x: 0.0, y: 0.66, width: 1.0, height: 0.34 }, OcrStep::Grayscale], separate.steps);
+
+        // The defaults of the fixed order preprocessing are the default steps.
+        let empty: OcrSettings = serde_json::from_str("{}").unwrap();
+        assert_eq!(OcrSettings::new(), empty);
+
+        for preset in default_ocr_presets() {
+            let saved: OcrSettings = serde_json::from_str(&serde_json::to_string(&preset.settings).unwrap()).unwrap();
+            assert_eq!(preset.settings, saved);
+        }
+    }
+
+    #[test]
+    fn test_upsert_preset() {
+        let mut presets = default_ocr_presets();
+        let count = presets.len();
+        upsert_ocr_preset(&mut presets, OcrPreset { name: "Slates".to_string(), creators: vec![], settings: OcrSettings::new() });
+        assert_eq!(count, presets.len());
+        assert!(presets.iter().find(|p| p.name == "Slates").unwrap().creators.is_empty());
+        upsert_ocr_preset(&mut presets, OcrPreset { name: "New".to_string(), creators: vec![], settings: OcrSettings::new() });
+        assert_eq!(count + 1, presets.len());
+    }
+}
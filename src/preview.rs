@@ -0,0 +1,211 @@
+use std::path::Path;
+use std::sync::mpsc::{channel, Sender, Receiver};
+use eframe::{egui, epi};
+
+/// The width the frames are scaled down to (if wider).
+static PREVIEW_MAX_WIDTH: u32 = 640;
+/// The frame duration assumed when the frame rate of the video is not known.
+static DEFAULT_FRAME_DURATION: f64 = 1.0 / 25.0;
+
+/// A decoded frame, as RGBA pixels.
+pub struct PreviewFrame {
+    pub time: f64,
+    pub size: [usize; 2],
+    pub pixels: Vec<u8>,
+    // The duration of the video and of a single frame (in seconds).
+    pub duration: f64,
+    pub frame_duration: f64,
+}
+
+/// Decodes the frames of a video (audio is ignored), so that they can be shown inside the app.
+pub struct FrameDecoder {
+    pub file: String,
+    input: ffmpeg::format::context::Input,
+    decoder: ffmpeg::codec::decoder::Video,
+    scaler: ffmpeg::software::scaling::context::Context,
+    stream_index: usize,
+    // Seconds per timestamp unit of the video stream.
+    time_base: f64,
+    pub frame_duration: f64,
+    pub duration: f64,
+    size: [usize; 2],
+}
+
+impl FrameDecoder {
+    pub fn open(file: &str) -> Result<Self, String> {
+        let _ = ffmpeg::init();
+        let input = ffmpeg::format::input(&Path::new(file)).map_err(|e| format!("Failed to open video: {}. Error: {}", file, e))?;
+        let (stream_index, time_base, frame_duration, decoder) = {
+            let stream = input.streams().best(ffmpeg::media::Type::Video).ok_or(format!("No video stream found in: {}", file))?;
+            let time_base = stream.time_base().0 as f64 / stream.time_base().1 as f64;
+            let rate = stream.avg_frame_rate();
+            let frame_duration = if rate.0 > 0 && rate.1 > 0 { rate.1 as f64 / rate.0 as f64 } else { DEFAULT_FRAME_DURATION };
+            let decoder = stream.codec().decoder().video().map_err(|e| format!("Failed to create decoder for: {}. Error: {}", file, e))?;
+            (stream.index(), time_base, frame_duration, decoder)
+        };
+        let (width, height) = preview_size(decoder.width(), decoder.height());
+        let scaler = ffmpeg::software::scaling::context::Context::get(decoder.format(), decoder.width(), decoder.height(),
+                                                                      ffmpeg::format::Pixel::RGBA, width, height,
+                                                                      ffmpeg::software::scaling::flag::Flags::BILINEAR)
+            .map_err(|e| format!("Failed to create scaler for: {}. Error: {}", file, e))?;
+        let duration = input.duration() as f64 / ffmpeg_sys::AV_TIME_BASE as f64;
+        Ok(FrameDecoder { file: file.to_string(), input, decoder, scaler, stream_index, time_base, frame_duration, duration, size: [width as usize, height as usize] })
+    }
+
+    /// The first frame shown at (or after) the time. Seeks to the previous key frame and decodes from there, so the frame is exact.
+    pub fn frame_at(&mut self, seconds: f64) -> Result<PreviewFrame, String> {
+        let target = (seconds * ffmpeg_sys::AV_TIME_BASE as f64) as i64;
+        self.input.seek(target, ..target).map_err(|e| format!("Failed to seek to: {}. Error: {}", seconds, e))?;
+        self.decoder.flush();
+        let mut decoded = ffmpeg::frame::Video::empty();
+        let mut last: Option<PreviewFrame> = None;
+        for (stream, packet) in self.input.packets() {
+            if stream.index() != self.stream_index {
+                continue;
+            }
+            if !self.decoder.decode(&packet, &mut decoded).unwrap_or(false) {
+                continue;
+            }
+            let time = decoded.timestamp().or_else(|| decoded.pts()).map(|t| t as f64 * self.time_base).unwrap_or(seconds);
+            // Frames before the target are only decoded, there is no need to convert them.
+            if time + self.frame_duration / 2.0 < seconds {
+                continue;
+            }
+            let mut rgba = ffmpeg::frame::Video::empty();
+            self.scaler.run(&decoded, &mut rgba).map_err(|e| format!("Failed to convert frame. Error: {}", e))?;
+            last = Some(PreviewFrame { time, size: self.size, pixels: rgba_pixels(&rgba, self.size), duration: self.duration, frame_duration: self.frame_duration });
+            break;
+        }
+        last.ok_or(format!("No frame found at: {}", seconds))
+    }
+}
+
+/// The size of the preview, keeping the aspect ratio of the video. Dimensions are kept even, as some pixel formats require it.
+pub fn preview_size(width: u32, height: u32) -> (u32, u32) {
+    if width <= PREVIEW_MAX_WIDTH || width == 0 {
+        return (width, height);
+    }
+    let scaled_height = (height as f64 * PREVIEW_MAX_WIDTH as f64 / width as f64).round() as u32;
+    (PREVIEW_MAX_WIDTH, scaled_height + scaled_height % 2)
+}
+
+/// The pixels of the frame without the padding ffmpeg adds at the end of each line.
+fn rgba_pixels(frame: &ffmpeg::frame::Video, size: [usize; 2]) -> Vec<u8> {
+    let stride = frame.stride(0);
+    let line = size[0] * 4;
+    frame.data(0).chunks(stride).take(size[1]).flat_map(|l| l[..line].to_vec()).collect()
+}
+
+pub struct PreviewRequest {
+    // Identifies the request, so that the frame can be matched with the latest request.
+    pub id: u64,
+    pub file: String,
+    pub time: f64,
+}
+
+/// A preview of a video inside the app. Frames are decoded in the background, skipping positions that were scrubbed past.
+pub struct VideoPreview {
+    pub file: String,
+    // The time of the frame shown.
+    pub time: f64,
+    // The time requested last (e.g. while scrubbing), which the frame shown catches up with.
+    pub position: f64,
+    // The id of the last request and of the request the frame shown (or the error) answers.
+    requested: u64,
+    answered: u64,
+    pub duration: f64,
+    pub frame_duration: f64,
+    pub texture: Option<(egui::TextureId, [usize; 2])>,
+    pub error: Option<String>,
+    sender: Sender<PreviewRequest>,
+    recv: Receiver<(u64, Result<PreviewFrame, String>)>,
+}
+
+impl VideoPreview {
+    pub fn new(file: &str, time: f64) -> Self {
+        let (sender, worker_recv) = channel::<PreviewRequest>();
+        let (worker_sender, recv) = channel();
+        std::thread::spawn(move || {
+            let mut decoder: Option<FrameDecoder> = None;
+            while let Ok(mut request) = worker_recv.recv() {
+                while let Ok(newer) = worker_recv.try_recv() {
+                    request = newer;
+                }
+                if decoder.as_ref().map(|d| d.file != request.file).unwrap_or(true) {
+                    decoder = match FrameDecoder::open(&request.file) {
+                        Ok(d) => Some(d),
+                        Err(e) => {
+                            let _ = worker_sender.send((request.id, Err(e)));
+                            continue;
+                        }
+                    };
+                }
+                let frame = decoder.as_mut().map(|d| d.frame_at(request.time)).unwrap_or(Err("No decoder".to_string()));
+                if worker_sender.send((request.id, frame)).is_err() {
+                    return;
+                }
+            }
+        });
+        let mut preview = VideoPreview { file: file.to_string(), time, position: time, requested: 0, answered: 0, duration: 0.0, frame_duration: DEFAULT_FRAME_DURATION, texture: None, error: None, sender, recv };
+        preview.request(time);
+        preview
+    }
+
+    /// Asks for the frame at the time, which is shown once decoded.
+    pub fn request(&mut self, time: f64) {
+        self.position = time.max(0.0);
+        self.requested += 1;
+        let _ = self.sender.send(PreviewRequest { id: self.requested, file: self.file.to_string(), time: self.position });
+    }
+
+    /// Steps from the frame shown or, if a frame is still being decoded, from the time requested.
+    pub fn step(&mut self, frames: i32) {
+        let from = if self.is_pending() { self.position } else { self.time };
+        self.request(from + frames as f64 * self.frame_duration);
+    }
+
+    /// Whether the frame of the last request has not been decoded yet.
+    pub fn is_pending(&self) -> bool {
+        self.answered < self.requested
+    }
+
+    /// Shows the frames decoded since the last update, freeing the textures no longer shown.
+    pub fn update(&mut self, frame: &epi::Frame) {
+        while let Ok((id, decoded)) = self.recv.try_recv() {
+            self.answered = id;
+            match decoded {
+                Ok(decoded) => {
+                    if let Some((texture, _)) = self.texture.take() {
+                        frame.free_texture(texture);
+                    }
+                    let image = epi::Image::from_rgba_unmultiplied(decoded.size, &decoded.pixels);
+                    self.texture = Some((frame.alloc_texture(image), decoded.size));
+                    // The position is left alone, as the frame may answer an older request.
+                    self.time = decoded.time;
+                    self.duration = decoded.duration;
+                    self.frame_duration = decoded.frame_duration;
+                    self.error = None;
+                },
+                Err(e) => self.error = Some(e),
+            }
+        }
+    }
+
+    pub fn close(&mut self, frame: &epi::Frame) {
+        if let Some((texture, _)) = self.texture.take() {
+            frame.free_texture(texture);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_preview_size() {
+        assert_eq!((640, 360), preview_size(1920, 1080));
+        assert_eq!((640, 362), preview_size(1000, 565));
+        assert_eq!((480, 270), preview_size(480, 270));
+    }
+}
@@ -0,0 +1,86 @@
+use crate::FanaticsProvider;
+use std::path::PathBuf;
+use std::sync::Arc;
+use crate::HttpSettings;
+use crate::Instructional;
+use crate::LocalProvider;
+use crate::FANATICS_PROVIDER;
+use crate::FANATICS_PROVIDER_NAME;
+use crate::LOCAL_PROVIDER;
+use crate::LOCAL_PROVIDER_NAME;
+
+/// The provider used when an instructional doesn't specify one.
+pub static DEFAULT_PROVIDER: &str = "bjj-fanatics";
+
+/// A source of instructional metadata (products, creators and chapter lists).
+/// Providers are shared with the background jobs, so they need to be thread safe.
+pub trait MetadataProvider: Send + Sync {
+    /// The identifier of the provider, as stored in the org file (`#+provider:`).
+    fn id(&self) -> &'static str;
+    /// A human readable name.
+    fn name(&self) -> &'static str;
+    /// The creators to show before the full list is requested.
+    fn popular_creators(&self) -> Vec<String>;
+    /// All the known creators.
+    fn list_creators(&self) -> Vec<String>;
+    /// The products matching the creator and title. Empty strings match everything.
+    fn search_products(&self, creator: String, title: String) -> Result<Vec<Instructional>, String>;
+    /// Like `search_products`, but only searches data that is already available locally (nothing is downloaded).
+    fn search_cached_products(&self, creator: String, title: String) -> Result<Vec<Instructional>, String> {
+        self.search_products(creator, title)
+    }
+    /// The raw chapter list (titles and timestamps) of the product found at the url.
+    fn fetch_chapters(&self, url: String) -> Result<String, String>;
+    /// The product (including metadata like description, tags and cover) found at the url, if known.
+    fn fetch_details(&self, _url: String) -> Result<Option<Instructional>, String> {
+        Ok(None)
+    }
+    /// Downloads the cover image found at the url and returns the path of the downloaded file.
+    fn download_cover(&self, url: String) -> Result<PathBuf, String> {
+        Err(format!("Cover images are not supported by this provider: {}", url))
+    }
+    /// Discards any cached data and fetches fresh data.
+    fn refresh(&self) -> Result<(), String>;
+}
+
+pub fn get_provider_ids() -> Vec<&'static str> {
+    vec![FANATICS_PROVIDER, LOCAL_PROVIDER]
+}
+
+/// The provider with the specified id, falling back to the default provider for unknown ids.
+pub fn get_provider(id: &str, http_settings: &HttpSettings) -> Arc<dyn MetadataProvider> {
+    match id.trim() {
+        id if id == LOCAL_PROVIDER => Arc::new(LocalProvider::new()),
+        id if id == FANATICS_PROVIDER => Arc::new(FanaticsProvider::new(http_settings)),
+        other => {
+            if !other.is_empty() {
+                println!("Unknown metadata provider: {}. Using: {}.", other, DEFAULT_PROVIDER);
+            }
+            Arc::new(FanaticsProvider::new(http_settings))
+        }
+    }
+}
+
+/// The name of the provider with the specified id, without creating the provider.
+pub fn get_provider_name(id: &str) -> &'static str {
+    match id.trim() {
+        id if id == LOCAL_PROVIDER => LOCAL_PROVIDER_NAME,
+        _ => FANATICS_PROVIDER_NAME,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_provider() {
+        let settings = HttpSettings::new();
+        assert_eq!("local", get_provider("local", &settings).id());
+        assert_eq!("bjj-fanatics", get_provider("bjj-fanatics", &settings).id());
+        assert_eq!(DEFAULT_PROVIDER, get_provider("", &settings).id());
+        assert_eq!(DEFAULT_PROVIDER, get_provider("unknown", &settings).id());
+        assert!(get_provider_ids().iter().all(|id| get_provider(id, &settings).id() == *id));
+        assert!(get_provider_ids().iter().all(|id| get_provider(id, &settings).name() == get_provider_name(id)));
+    }
+}
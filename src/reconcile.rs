@@ -0,0 +1,283 @@
+use crate::Scene;
+use crate::similarity;
+
+/// Pairs scoring below this value are never aligned.
+static MIN_MATCH_SCORE: f32 = 0.4;
+/// Start times further apart than this (in seconds) don't contribute to the score of a pair.
+static MAX_START_DISTANCE: f32 = 60.0;
+
+/// A detected scene, a scraped scene, or both when the two were aligned.
+#[derive(Debug, Clone)]
+pub struct ReconciledScene {
+    pub detected: Option<Scene>,
+    pub scraped: Option<Scene>,
+    pub score: f32,
+    // The title to use for the merged scene (editable by the user).
+    pub title: String,
+    // Whether the scene is going to be part of the merged scene list.
+    pub include: bool,
+}
+
+impl ReconciledScene {
+    pub fn is_matched(&self) -> bool {
+        self.detected.is_some() && self.scraped.is_some()
+    }
+}
+
+/// How likely it is that the two scenes are the same. Combines title similarity and start time proximity.
+pub fn match_score(detected: &Scene, scraped: &Scene) -> f32 {
+    let distance = if detected.start > scraped.start { detected.start - scraped.start } else { scraped.start - detected.start } as f32;
+    let time_score = (1.0 - distance / MAX_START_DISTANCE).max(0.0);
+    0.7 * similarity(&detected.title, &scraped.title) + 0.3 * time_score
+}
+
+/// Aligns the two scene sequences (Needleman-Wunsch), preserving the order of both.
+/// Returns the indexes of the aligned detected / scraped scenes (either side may be missing) along with the pair score.
+pub fn align_scenes(detected: &Vec<Scene>, scraped: &Vec<Scene>) -> Vec<(Option<usize>, Option<usize>, f32)> {
+    let n = detected.len();
+    let m = scraped.len();
+    let scores: Vec<Vec<f32>> = detected.iter()
+        .map(|d| scraped.iter().map(|s| match_score(d, s)).collect())
+        .collect();
+
+    // best[i][j]: the best total for aligning the first i detected with the first j scraped scenes.
+    // Gaps cost nothing, matches are rewarded by how much they exceed the minimum score.
+    let mut best = vec![vec![0.0_f32; m + 1]; n + 1];
+    for i in 1..=n {
+        for j in 1..=m {
+            let pair = scores[i - 1][j - 1];
+            let matched = if pair >= MIN_MATCH_SCORE { best[i - 1][j - 1] + pair - MIN_MATCH_SCORE } else { f32::MIN };
+            best[i][j] = matched.max(best[i - 1][j]).max(best[i][j - 1]);
+        }
+    }
+
+    let mut result: Vec<(Option<usize>, Option<usize>, f32)> = vec![];
+    let (mut i, mut j) = (n, m);
+    while i > 0 || j > 0 {
+        if i > 0 && j > 0 {
+            let pair = scores[i - 1][j - 1];
+            if pair >= MIN_MATCH_SCORE && best[i][j] == best[i - 1][j - 1] + pair - MIN_MATCH_SCORE {
+                result.push((Some(i - 1), Some(j - 1), pair));
+                i -= 1;
+                j -= 1;
+                continue;
+            }
+        }
+        if i > 0 && (j == 0 || best[i][j] == best[i - 1][j]) {
+            result.push((Some(i - 1), None, 0.0));
+            i -= 1;
+        } else {
+            result.push((None, Some(j - 1), 0.0));
+            j -= 1;
+        }
+    }
+    result.reverse();
+    result
+}
+
+/// Aligns the detected with the scraped scenes, and proposes a merged scene for each entry.
+/// Matched entries get the scraped title, unmatched detected scenes are kept as is and unmatched scraped scenes are excluded by default.
+pub fn reconcile_scenes(detected: &Vec<Scene>, scraped: &Vec<Scene>) -> Vec<ReconciledScene> {
+    align_scenes(detected, scraped).into_iter()
+        .map(|(d, s, score)| {
+            let detected = d.map(|i| detected[i].clone());
+            let scraped = s.map(|j| scraped[j].clone());
+            let title = scraped.as_ref().or(detected.as_ref()).map(|s| s.title.to_string()).unwrap_or_default();
+            let include = detected.is_some();
+            ReconciledScene { detected, scraped, score, title, include }
+        })
+        .collect()
+}
+
+/// Creates the merged scene list: detected timings (when available) with the chosen titles.
+pub fn apply_reconciliation(reconciled: &Vec<ReconciledScene>, file: &str) -> Vec<Scene> {
+    let mut scenes: Vec<Scene> = reconciled.iter()
+        .filter(|r| r.include)
+        .filter_map(|r| {
+            let timing = r.detected.as_ref().or(r.scraped.as_ref())?;
+            let mut scene = timing.clone();
+            scene.title = r.title.to_string();
+            scene.file = file.to_string();
+            Some(scene)
+        })
+        .collect();
+    scenes.sort_by_key(|s| s.start);
+    scenes.iter_mut().enumerate().for_each(|(index, s)| s.index = index);
+    scenes
+}
+
+/// What to take from the re-scraped scenes when merging them into the existing ones.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MergeOptions {
+    pub update_timings: bool,
+    pub update_titles: bool,
+    // Whether scraped scenes that don't match any existing scene are added.
+    pub add_new_scenes: bool,
+}
+
+impl MergeOptions {
+    pub fn new() -> Self {
+        MergeOptions { update_timings: true, update_titles: true, add_new_scenes: true }
+    }
+}
+
+impl Default for MergeOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// An existing scene (identified by its index), a re-scraped scene, or both, along with the scene that results from merging them.
+#[derive(Debug, Clone)]
+pub struct MergedScene {
+    pub existing: Option<usize>,
+    pub before: Option<Scene>,
+    pub scraped: Option<Scene>,
+    pub after: Option<Scene>,
+    pub score: f32,
+}
+
+impl MergedScene {
+    /// Whether merging adds the scene or changes its title or timings.
+    pub fn is_changed(&self) -> bool {
+        match (&self.before, &self.after) {
+            (Some(b), Some(a)) => b.title != a.title || b.start != a.start || b.end != a.end,
+            (None, Some(_)) => true,
+            _ => false,
+        }
+    }
+}
+
+/// Merges the re-scraped scenes into the existing ones, without losing any work (notes, labels and scenes that were not scraped).
+/// Scenes are aligned by title similarity and position and only the timings and / or titles selected in the options are updated.
+pub fn merge_scenes(existing: &Vec<Scene>, scraped: &Vec<Scene>, options: &MergeOptions) -> Vec<MergedScene> {
+    align_scenes(existing, scraped).into_iter()
+        .map(|(e, s, score)| {
+            let before = e.map(|i| existing[i].clone());
+            let scraped = s.map(|j| scraped[j].clone());
+            let after = match (&before, &scraped) {
+                (Some(b), Some(s)) => {
+                    let mut scene = b.clone();
+                    if options.update_timings {
+                        scene.start = s.start;
+                        scene.end = s.end;
+                    }
+                    if options.update_titles {
+                        scene.title = s.title.to_string();
+                    }
+                    Some(scene)
+                },
+                (Some(b), None) => Some(b.clone()),
+                (None, Some(s)) if options.add_new_scenes => Some(s.clone()),
+                _ => None,
+            };
+            MergedScene { existing: e, before, scraped, after, score }
+        })
+        .collect()
+}
+
+/// Keeps the existing scenes of the specified rows of the merge as they were (and doesn't add the new scenes), e.g. when some of the changes are rejected in the preview.
+pub fn keep_existing(merged: &mut Vec<MergedScene>, rows: &[usize]) {
+    merged.iter_mut().enumerate()
+        .filter(|(row, _)| rows.contains(row))
+        .for_each(|(_, m)| m.after = m.before.clone());
+}
+
+/// Creates the merged scene list, along with the index of the existing scene each one originates from (if any).
+pub fn apply_merge(merged: &Vec<MergedScene>, file: &str) -> Vec<(Option<usize>, Scene)> {
+    let mut scenes: Vec<(Option<usize>, Scene)> = merged.iter()
+        .filter_map(|m| m.after.as_ref().map(|s| (m.existing, s.clone())))
+        .collect();
+    scenes.sort_by_key(|(_, s)| s.start);
+    scenes.iter_mut().enumerate().for_each(|(index, (_, s))| {
+        s.index = index;
+        s.file = file.to_string();
+    });
+    scenes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scene(title: &str, start: usize, end: usize) -> Scene {
+        Scene { index: 0, title: title.to_string(), file: "".to_string(), text: "".to_string(), labels: vec![], start, end, speed: 1.0 }
+    }
+
+    #[test]
+    fn test_align_scenes() {
+        let detected = vec![scene("Intrc", 3, 62), scene("Rear Nakcd Chke", 62, 300), scene("Credits", 300, 400), scene("Arm Bar", 400, 500)];
+        let scraped = vec![scene("Intro", 0, 60), scene("Rear Naked Choke", 60, 120), scene("Arm Bar", 420, 500), scene("Bonus", 500, 600)];
+        let alignment = align_scenes(&detected, &scraped);
+        assert_eq!(vec![(Some(0), Some(0)), (Some(1), Some(1)), (Some(2), None), (Some(3), Some(2)), (None, Some(3))],
+                   alignment.iter().map(|(d, s, _)| (*d, *s)).collect::<Vec<(Option<usize>, Option<usize>)>>());
+    }
+
+    #[test]
+    fn test_align_with_stray_entry() {
+        // A single stray scraped entry should not shift all the titles that follow.
+        let detected = vec![scene("Guard Retention", 0, 100), scene("Passing", 100, 200), scene("Pinning", 200, 300)];
+        let scraped = vec![scene("Guard Retention", 0, 100), scene("Intro To Passing Concepts", 95, 98), scene("Passing", 100, 200), scene("Pinning", 200, 300)];
+        let reconciled = reconcile_scenes(&detected, &scraped);
+        assert_eq!(4, reconciled.len());
+        assert_eq!(3, reconciled.iter().filter(|r| r.is_matched()).count());
+        assert!(!reconciled[1].is_matched() && !reconciled[1].include);
+    }
+
+    #[test]
+    fn test_apply_reconciliation() {
+        let detected = vec![scene("Intrc", 3, 62), scene("Rear Nakcd Chke", 62, 300)];
+        let scraped = vec![scene("Intro", 0, 60), scene("Rear Naked Choke", 60, 120), scene("Bonus", 500, 600)];
+        let mut reconciled = reconcile_scenes(&detected, &scraped);
+        let scenes = apply_reconciliation(&reconciled, "vol1.mp4");
+        assert_eq!(2, scenes.len());
+        assert_eq!("Intro", scenes[0].title);
+        assert_eq!(3, scenes[0].start);
+        assert_eq!("Rear Naked Choke", scenes[1].title);
+        assert_eq!(62, scenes[1].start);
+        assert_eq!(300, scenes[1].end);
+        assert_eq!("vol1.mp4", scenes[1].file);
+
+        reconciled[2].include = true;
+        let scenes = apply_reconciliation(&reconciled, "vol1.mp4");
+        assert_eq!(3, scenes.len());
+        assert_eq!("Bonus", scenes[2].title);
+        assert_eq!(2, scenes[2].index);
+    }
+
+    #[test]
+    fn test_merge_scenes() {
+        let mut existing = vec![scene("Intro", 0, 58), scene("Rear Naked Chke", 58, 300), scene("My Notes", 300, 320)];
+        existing[1].text = "Keep the elbow tight".to_string();
+        existing[1].labels = vec!["favorite".to_string()];
+        let scraped = vec![scene("Intro", 0, 60), scene("Rear Naked Choke", 60, 300), scene("Bonus", 500, 600)];
+
+        let options = MergeOptions { update_timings: false, update_titles: true, add_new_scenes: false };
+        let merged = merge_scenes(&existing, &scraped, &options);
+        assert_eq!(1, merged.iter().filter(|m| m.is_changed()).count());
+        let scenes = apply_merge(&merged, "vol1.mp4");
+        assert_eq!(3, scenes.len());
+        assert_eq!((Some(1), "Rear Naked Choke".to_string(), 58), (scenes[1].0, scenes[1].1.title.to_string(), scenes[1].1.start));
+        assert_eq!("Keep the elbow tight", scenes[1].1.text);
+        assert_eq!(vec!["favorite".to_string()], scenes[1].1.labels);
+        assert_eq!("My Notes", scenes[2].1.title);
+
+        let merged = merge_scenes(&existing, &scraped, &MergeOptions::new());
+        assert_eq!(3, merged.iter().filter(|m| m.is_changed()).count());
+        let scenes = apply_merge(&merged, "vol1.mp4");
+        assert_eq!(4, scenes.len());
+        assert_eq!((60, 300), (scenes[1].1.start, scenes[1].1.end));
+        assert_eq!("Keep the elbow tight", scenes[1].1.text);
+        assert_eq!((None, 3, "vol1.mp4".to_string()), (scenes[3].0, scenes[3].1.index, scenes[3].1.file.to_string()));
+
+        // Rejected changes keep the existing scene and new scenes are not added.
+        let mut merged = merge_scenes(&existing, &scraped, &MergeOptions::new());
+        let rows: Vec<usize> = merged.iter().enumerate().filter(|(_, m)| m.scraped.as_ref().map(|s| s.title != "Intro").unwrap_or(false)).map(|(row, _)| row).collect();
+        keep_existing(&mut merged, &rows);
+        assert_eq!(1, merged.iter().filter(|m| m.is_changed()).count());
+        let scenes = apply_merge(&merged, "vol1.mp4");
+        assert_eq!(3, scenes.len());
+        assert_eq!((0, 60), (scenes[0].1.start, scenes[0].1.end));
+        assert_eq!(("Rear Naked Chke".to_string(), 58), (scenes[1].1.title.to_string(), scenes[1].1.start));
+    }
+}
@@ -0,0 +1,93 @@
+use crate::normalize;
+use crate::similarity;
+
+/// Query tokens that are less similar than this to every token of the text, don't match.
+static MIN_TOKEN_SIMILARITY: f32 = 0.75;
+/// Results scoring less than this are dropped.
+static MIN_SEARCH_SCORE: f32 = 0.5;
+
+/// How well a single query token matches the best token of the text: 1.0 for an exact match, 0.9 for a prefix and less for typos.
+fn token_score(query_token: &str, tokens: &Vec<&str>) -> f32 {
+    tokens.iter()
+        .map(|t| {
+            if *t == query_token {
+                1.0
+            } else if t.starts_with(query_token) {
+                0.9
+            } else {
+                let s = similarity(query_token, t);
+                if s >= MIN_TOKEN_SIMILARITY { s * 0.8 } else { 0.0 }
+            }
+        })
+        .fold(0.0, f32::max)
+}
+
+/// How well the query matches the text (case and accent insensitive), from 0.0 to 1.0. The order of the tokens doesn't matter.
+/// Returns None for empty queries.
+pub fn query_score(query: &str, text: &str) -> Option<f32> {
+    let query = normalize(query);
+    let text = normalize(text);
+    let query_tokens: Vec<&str> = query.split_whitespace().collect();
+    if query_tokens.is_empty() {
+        return None;
+    }
+    let tokens: Vec<&str> = text.split_whitespace().collect();
+    Some(query_tokens.iter().map(|q| token_score(q, &tokens)).sum::<f32>() / query_tokens.len() as f32)
+}
+
+/// Ranks the items by how well the creator query matches their vendor and the title query matches their title.
+/// The title query may also contain words of the vendor (e.g. `danaher back`).
+/// Items that don't match are dropped. Empty queries match everything.
+pub fn rank_by_match<T, F>(items: Vec<T>, creator: &str, title: &str, key: F) -> Vec<T> where F: Fn(&T) -> (String, String) {
+    let mut scored: Vec<(f32, String, T)> = items.into_iter()
+        .filter_map(|item| {
+            let (vendor, item_title) = key(&item);
+            let creator_score = query_score(creator, &vendor);
+            let title_score = query_score(title, &format!("{} {}", item_title, vendor));
+            let scores: Vec<f32> = creator_score.into_iter().chain(title_score.into_iter()).collect();
+            let score = if scores.is_empty() { 1.0 } else { scores.iter().sum::<f32>() / scores.len() as f32 };
+            // Each query needs to match on its own, a perfect title doesn't make up for the wrong creator.
+            if scores.iter().any(|s| *s < MIN_SEARCH_SCORE) {
+                return None;
+            }
+            Some((score, item_title, item))
+        })
+        .collect();
+    scored.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(std::cmp::Ordering::Equal).then_with(|| a.1.cmp(&b.1)));
+    scored.into_iter().map(|(_, _, item)| item).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn products() -> Vec<(String, String)> {
+        vec![
+            ("John Danaher".to_string(), "Back Attacks: Enter The System By John Danaher".to_string()),
+            ("John Danaher".to_string(), "Leglocks: Enter The System By John Danaher".to_string()),
+            ("Gordon Ryan".to_string(), "The Back System By Gordon Ryan".to_string()),
+            ("André Galvão".to_string(), "Drill To Win By Andre Galvao".to_string()),
+        ]
+    }
+
+    fn search(creator: &str, title: &str) -> Vec<String> {
+        rank_by_match(products(), creator, title, |p| p.clone()).into_iter().map(|p| p.1).collect()
+    }
+
+    #[test]
+    fn test_query_score() {
+        assert_eq!(None, query_score("", "Back Attacks"));
+        assert_eq!(Some(1.0), query_score("ATTACKS back", "Back Attacks"));
+        assert_eq!(Some(0.0), query_score("guard", "Back Attacks"));
+        assert!(query_score("atacks", "Back Attacks").unwrap() > 0.5);
+    }
+
+    #[test]
+    fn test_rank_by_match() {
+        assert_eq!("Back Attacks: Enter The System By John Danaher", search("", "danaher back")[0]);
+        assert_eq!(vec!["Back Attacks: Enter The System By John Danaher", "Leglocks: Enter The System By John Danaher"], search("danaher", ""));
+        assert_eq!(vec!["The Back System By Gordon Ryan"], search("gordon", "back"));
+        assert_eq!(vec!["Drill To Win By Andre Galvao"], search("andre galvao", ""));
+        assert_eq!(4, search("", "").len());
+    }
+}
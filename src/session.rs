@@ -0,0 +1,242 @@
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
+use std::path::PathBuf;
+use crate::Instructional;
+use crate::Scene;
+use crate::get_data_dir;
+
+/// How close (in seconds) to the end of a scene the playback has to be, for the scene to be considered watched.
+static END_OF_SCENE_MARGIN: f64 = 1.0;
+
+/// A scene queued for a study session, along with the instructional it belongs to.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct QueuedScene {
+    pub instructional: String,
+    pub title: String,
+    pub file: String,
+    pub start: usize,
+    pub end: usize,
+    #[serde(default)]
+    pub watched: bool,
+    #[serde(default = "default_speed")]
+    pub speed: f64,
+}
+
+fn default_speed() -> f64 {
+    1.0
+}
+
+impl QueuedScene {
+    pub fn new(instructional: &Instructional, scene: &Scene) -> Self {
+        QueuedScene {
+            instructional: format!("{} - {}", instructional.creator, instructional.title),
+            title: scene.title.to_string(),
+            file: scene.file.to_string(),
+            start: scene.start,
+            end: scene.end,
+            watched: false,
+            speed: scene.speed,
+        }
+    }
+}
+
+/// Scenes (possibly from different volumes or instructionals) to watch back to back.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct StudySession {
+    pub scenes: Vec<QueuedScene>,
+    // The index of the scene being watched.
+    pub current: usize,
+    // The playback time reached in the current scene, so that it can be resumed.
+    #[serde(default)]
+    pub position: f64,
+}
+
+impl StudySession {
+    pub fn new() -> Self {
+        StudySession { scenes: vec![], current: 0, position: 0.0 }
+    }
+
+    pub fn add(&mut self, scene: QueuedScene) {
+        self.scenes.push(scene);
+    }
+
+    pub fn remove(&mut self, index: usize) {
+        if index >= self.scenes.len() {
+            return;
+        }
+        self.scenes.remove(index);
+        if index < self.current || self.current >= self.scenes.len() {
+            self.current = self.current.saturating_sub(1);
+        }
+    }
+
+    /// Moves the scene one position up (or down), keeping track of the current scene.
+    pub fn swap(&mut self, a: usize, b: usize) {
+        if a >= self.scenes.len() || b >= self.scenes.len() {
+            return;
+        }
+        self.scenes.swap(a, b);
+        if self.current == a {
+            self.current = b;
+        } else if self.current == b {
+            self.current = a;
+        }
+    }
+
+    pub fn current_scene(&self) -> Option<&QueuedScene> {
+        self.scenes.get(self.current)
+    }
+
+    /// Jumps to the scene (e.g. when selected from the queue). Jumping past the last scene finishes the session.
+    pub fn select(&mut self, index: usize) -> Option<&QueuedScene> {
+        self.current = index.min(self.scenes.len());
+        self.position = 0.0;
+        self.current_scene()
+    }
+
+    /// Marks the current scene as watched and moves to the next one.
+    pub fn next(&mut self) -> Option<&QueuedScene> {
+        if let Some(scene) = self.scenes.get_mut(self.current) {
+            scene.watched = true;
+        }
+        self.select(self.current + 1)
+    }
+
+    /// Moves to the next scene, without marking the current one as watched.
+    pub fn skip(&mut self) -> Option<&QueuedScene> {
+        self.select(self.current + 1)
+    }
+
+    pub fn previous(&mut self) -> Option<&QueuedScene> {
+        if self.current == 0 {
+            return None;
+        }
+        self.select(self.current - 1)
+    }
+
+    /// The time to start the current scene from, taking into account where it was interrupted.
+    pub fn resume_time(&self) -> Option<usize> {
+        let scene = self.current_scene()?;
+        let position = self.position as usize;
+        if position > scene.start && (scene.end == 0 || position < scene.end) {
+            return Some(position);
+        }
+        Some(scene.start)
+    }
+
+    /// Keeps track of the playback time of the current scene. Returns true when the end of the scene (or of the video, for scenes without a known end) was reached.
+    pub fn track(&mut self, path: &str, playback_time: f64, duration: f64) -> bool {
+        let scene = match self.current_scene() {
+            Some(scene) if scene.file == path => scene,
+            _ => return false,
+        };
+        let end = if scene.end > 0 { scene.end as f64 } else { duration };
+        // The playback stops at the end of the scene, so a time well past it is left over from a previous scene of the same video.
+        let reached_end = end > 0.0 && playback_time + END_OF_SCENE_MARGIN >= end && playback_time <= end + END_OF_SCENE_MARGIN;
+        if playback_time >= scene.start as f64 && (end <= 0.0 || playback_time <= end + END_OF_SCENE_MARGIN) {
+            self.position = playback_time;
+        }
+        reached_end
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.current >= self.scenes.len()
+    }
+}
+
+/// What the controls of the study session window ask for.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SessionAction {
+    Play,
+    Previous,
+    // Marks the current scene as watched and plays the next one.
+    Next,
+    // Plays the next scene without marking the current one as watched.
+    Skip,
+    Stop,
+    Clear,
+}
+
+impl Default for StudySession {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub fn get_study_session_file() -> PathBuf {
+    get_data_dir().join("study-session.json")
+}
+
+pub fn load_study_session() -> StudySession {
+    let path = get_study_session_file();
+    if !path.exists() {
+        return StudySession::new();
+    }
+    let file = match File::open(&path) {
+        Ok(file) => file,
+        Err(e) => {
+            println!("Failed to open study session file: {}. Starting a new one. Error: {}", path.display(), e);
+            return StudySession::new();
+        }
+    };
+    let reader = BufReader::new(file);
+    match serde_json::from_reader::<_, StudySession>(reader) {
+        Ok(session) => session,
+        Err(e) => {
+            println!("Failed to read study session from: {}. Starting a new one. Error: {}", path.display(), e);
+            StudySession::new()
+        }
+    }
+}
+
+pub fn save_study_session(session: &StudySession) {
+    let path = get_study_session_file();
+    std::fs::create_dir_all(path.parent().expect("Failed to find data dir!")).expect("Failed to create data dir!");
+    let file = File::create(path).expect("Failed to create study session file!");
+    let mut out = BufWriter::new(file);
+    out.write_all(serde_json::to_string_pretty(session).expect("Failed to serialize study session!").as_bytes()).expect("Failed to write study session!");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn queued(file: &str, start: usize, end: usize) -> QueuedScene {
+        QueuedScene { instructional: "John Danaher - Back Attacks".to_string(), title: format!("{} {}", file, start), file: file.to_string(), start, end, watched: false, speed: 1.0 }
+    }
+
+    #[test]
+    fn test_study_session() {
+        let mut session = StudySession::new();
+        session.add(queued("vol1.mp4", 60, 120));
+        session.add(queued("vol3.mp4", 0, 30));
+        session.add(queued("vol2.mp4", 300, 360));
+
+        assert!(!session.track("vol3.mp4", 119.5, 600.0));
+        assert!(!session.track("vol1.mp4", 90.0, 600.0));
+        assert_eq!(Some(90), session.resume_time());
+        assert!(!session.track("vol1.mp4", 300.0, 600.0));
+        assert!(session.track("vol1.mp4", 119.5, 600.0));
+
+        assert_eq!(Some("vol3.mp4".to_string()), session.next().map(|s| s.file.to_string()));
+        assert!(session.scenes[0].watched);
+        assert_eq!(Some(0), session.resume_time());
+        assert_eq!(Some("vol2.mp4".to_string()), session.skip().map(|s| s.file.to_string()));
+        assert!(!session.scenes[1].watched);
+        assert_eq!(Some("vol3.mp4".to_string()), session.previous().map(|s| s.file.to_string()));
+
+        session.swap(1, 2);
+        assert_eq!(2, session.current);
+        session.remove(0);
+        assert_eq!(1, session.current);
+        assert_eq!("vol3.mp4", session.current_scene().unwrap().file);
+        assert!(session.next().is_none());
+        assert!(session.is_finished());
+
+        // Scenes without a known end finish with the video.
+        session.add(queued("vol4.mp4", 60, 0));
+        assert!(!session.track("vol4.mp4", 1200.0, 0.0));
+        assert!(!session.track("vol4.mp4", 1200.0, 1800.0));
+        assert!(session.track("vol4.mp4", 1799.5, 1800.0));
+    }
+}
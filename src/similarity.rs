@@ -0,0 +1,86 @@
+/// Lowercases the text, removes accents and keeps only alphanumeric words separated by a single space.
+pub fn normalize(s: &str) -> String {
+    s.to_lowercase()
+        .chars()
+        .map(fold_accent)
+        .map(|c| if c.is_alphanumeric() { c } else { ' ' })
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<&str>>()
+        .join(" ")
+}
+
+/// Replaces accented latin characters with their plain counterparts (e.g. `ã` with `a`).
+pub fn fold_accent(c: char) -> char {
+    match c {
+        'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' => 'a',
+        'ç' => 'c',
+        'è' | 'é' | 'ê' | 'ë' => 'e',
+        'ì' | 'í' | 'î' | 'ï' => 'i',
+        'ñ' => 'n',
+        'ò' | 'ó' | 'ô' | 'õ' | 'ö' | 'ø' => 'o',
+        'ù' | 'ú' | 'û' | 'ü' => 'u',
+        'ý' | 'ÿ' => 'y',
+        _ => c,
+    }
+}
+
+/// The Levenshtein distance between the two strings (counted in characters).
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    if a.is_empty() {
+        return b.len();
+    }
+    if b.is_empty() {
+        return a.len();
+    }
+
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current: Vec<usize> = vec![0; b.len() + 1];
+    for i in 1..=a.len() {
+        current[0] = i;
+        for j in 1..=b.len() {
+            let cost = if a[i - 1] == b[j - 1] { 0 } else { 1 };
+            current[j] = (previous[j] + 1)
+                .min(current[j - 1] + 1)
+                .min(previous[j - 1] + cost);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+    previous[b.len()]
+}
+
+/// A score from 0.0 (nothing in common) to 1.0 (identical) based on the edit distance of the normalized strings.
+pub fn similarity(a: &str, b: &str) -> f32 {
+    let a = normalize(a);
+    let b = normalize(b);
+    let len = a.chars().count().max(b.chars().count());
+    if len == 0 {
+        return 1.0;
+    }
+    1.0 - edit_distance(&a, &b) as f32 / len as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(0, edit_distance("", ""));
+        assert_eq!(3, edit_distance("abc", ""));
+        assert_eq!(3, edit_distance("", "abc"));
+        assert_eq!(0, edit_distance("armbar", "armbar"));
+        assert_eq!(1, edit_distance("armbar", "arnbar"));
+        assert_eq!(3, edit_distance("kitten", "sitting"));
+    }
+
+    #[test]
+    fn test_similarity() {
+        assert_eq!(1.0, similarity("Back Attacks", "back  attacks!"));
+        assert_eq!(0.0, similarity("abc", "xyz"));
+        assert!(similarity("Rear Naked Choke", "Rear Nakcd Choke") > 0.9);
+        assert_eq!(1.0, similarity("Galvão", "galvao"));
+    }
+}
@@ -0,0 +1,439 @@
+use regex::Regex;
+use crate::Scene;
+use crate::clean_title;
+use crate::time_to_seconds;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DiagnosticKind {
+    // The line contains neither a title nor a time.
+    Unparsed,
+    // A title that was never paired with a time.
+    MissingTime,
+    // A time that was never paired with a title.
+    MissingTitle,
+    // The number of title lines doesn't match the number of time lines.
+    CountMismatch,
+}
+
+/// A problem found while parsing the timestamps text. Line numbers start from 1 (0 refers to the whole text).
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimestampDiagnostic {
+    pub line: usize,
+    pub text: String,
+    pub kind: DiagnosticKind,
+    pub message: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct ParsedTimestamps {
+    pub volumes: Vec<Vec<Scene>>,
+    // The header of each volume (e.g. `Volume 1: Fundamentals`), or a generated name when there are no headers.
+    pub volume_titles: Vec<String>,
+    // Whether the volumes were split using explicit headers, or guessed from the start times.
+    pub explicit_volumes: bool,
+    pub diagnostics: Vec<TimestampDiagnostic>,
+}
+
+/// What a single line of the text holds.
+#[derive(Debug, Clone, PartialEq)]
+enum Line {
+    Title(String),
+    // Start (and optionally end) time.
+    Time(usize, Option<usize>),
+    // The length of the chapter, e.g. `5m 30s`.
+    Length(usize),
+    // A title along with its start time (and optionally end time) or length.
+    Entry(String, Timing),
+    // A volume header, e.g. `Volume 1`, `Part 2`, `Disc 3` or `Video 4`.
+    Header(String),
+    Unparsed,
+}
+
+/// A chapter or the start of a new volume.
+#[derive(Debug, Clone, PartialEq)]
+enum Item {
+    Header(String),
+    Chapter(String, Timing),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Timing {
+    Start(usize, Option<usize>),
+    Length(usize),
+}
+
+struct Patterns {
+    range: Regex,
+    time: Regex,
+    length: Regex,
+    length_part: Regex,
+    numbering: Regex,
+    has_text: Regex,
+    header: Regex,
+}
+
+thread_local! {
+    // Compiled once (per thread), as the timestamps are parsed again whenever they are edited.
+    static PATTERNS: Patterns = Patterns::new();
+}
+
+impl Patterns {
+    fn new() -> Patterns {
+        let time = r"(?:\d{1,2}:)?\d{1,2}:\d{2}";
+        Patterns {
+            range: Regex::new(&format!(r"({})\s*(?:-|–|to)\s*({})", time, time)).unwrap(),
+            time: Regex::new(&format!(r"\b({})\b", time)).unwrap(),
+            length: Regex::new(r"(?i)\b(?:\d+\s*(?:hours|hour|hrs|hr|h|minutes|minute|mins|min|m|seconds|second|secs|sec|s)\b\s*)+").unwrap(),
+            length_part: Regex::new(r"(?i)(\d+)\s*([hms])").unwrap(),
+            numbering: Regex::new(r"^\s*(?:(?:chapter|lesson|part)\s+)?\d+\s*[.):-]\s+").unwrap(),
+            has_text: Regex::new(r"[a-zA-Z]").unwrap(),
+            header: Regex::new(r"(?i)^(?:volume|vol\.?|part|disc|disk|video)\s*#?\s*(\d+|[ivx]+)\b\s*(?:[:.\-–|]\s*.*|\(.*\))?$").unwrap(),
+        }
+    }
+}
+
+/// Parses the timestamps text into volumes of scenes.
+pub fn extract_timestamps(body: String) -> Vec<Vec<Scene>> {
+    parse_timestamps(&body).volumes
+}
+
+/// Parses the timestamps text into volumes of scenes, along with diagnostics for the lines that could not be used.
+///
+/// Supported formats (one chapter per line, or title and time in consecutive lines):
+/// - `Title` followed by `00:00:00 - 00:10:00` (or the other way around)
+/// - `Title 12:34` / `12:34 Title` / `1. Title (12:34)`
+/// - Tab separated tables, e.g. `1\tTitle\t12:34`
+/// - Lengths instead of start times, e.g. `Title 5m 30s`
+///
+/// A new volume starts every time the start time goes back.
+pub fn parse_timestamps(body: &str) -> ParsedTimestamps {
+    PATTERNS.with(|patterns| parse_timestamps_with(body, patterns))
+}
+
+fn parse_timestamps_with(body: &str, patterns: &Patterns) -> ParsedTimestamps {
+    let mut diagnostics: Vec<TimestampDiagnostic> = vec![];
+    // The chapters and volume headers found so far.
+    let mut items: Vec<Item> = vec![];
+
+    let mut pending_title: Option<(usize, String)> = None;
+    let mut pending_timing: Option<(usize, String, Timing)> = None;
+    let mut title_lines = 0;
+    let mut time_lines = 0;
+
+    let mut lines: Vec<(usize, &str, Line)> = body.lines()
+        .enumerate()
+        .map(|(i, raw)| (i + 1, raw.trim()))
+        .filter(|(_, text)| !text.is_empty())
+        .map(|(number, text)| (number, text, parse_line(text, patterns)))
+        .collect();
+
+    // A header that is followed by a time, is actually the title of a chapter (e.g. a chapter called `Part 1`).
+    for i in 0..lines.len() {
+        if let Line::Header(_) = lines[i].2 {
+            let followed_by_time = lines.get(i + 1)
+                .map(|(_, _, next)| matches!(next, Line::Time(_, _) | Line::Length(_)))
+                .unwrap_or(false);
+            if followed_by_time {
+                lines[i].2 = Line::Title(lines[i].1.to_string());
+            }
+        }
+    }
+
+    for (number, text, line) in lines {
+        match line {
+            Line::Header(header) => {
+                if let Some((n, t)) = pending_title.take() {
+                    diagnostics.push(missing_time(n, &t));
+                }
+                if let Some((n, t, _)) = pending_timing.take() {
+                    diagnostics.push(missing_title(n, &t));
+                }
+                items.push(Item::Header(header));
+            },
+            Line::Entry(title, timing) => {
+                if let Some((n, t)) = pending_title.take() {
+                    diagnostics.push(missing_time(n, &t));
+                }
+                if let Some((n, t, _)) = pending_timing.take() {
+                    diagnostics.push(missing_title(n, &t));
+                }
+                items.push(Item::Chapter(title, timing));
+            },
+            Line::Title(title) => {
+                title_lines += 1;
+                if let Some((_, _, timing)) = pending_timing.take() {
+                    items.push(Item::Chapter(title, timing));
+                } else {
+                    if let Some((n, t)) = pending_title.take() {
+                        diagnostics.push(missing_time(n, &t));
+                    }
+                    pending_title = Some((number, title));
+                }
+            },
+            Line::Time(start, end) => {
+                time_lines += 1;
+                let timing = Timing::Start(start, end);
+                if let Some((_, title)) = pending_title.take() {
+                    items.push(Item::Chapter(title, timing));
+                } else {
+                    if let Some((n, t, _)) = pending_timing.take() {
+                        diagnostics.push(missing_title(n, &t));
+                    }
+                    pending_timing = Some((number, text.to_string(), timing));
+                }
+            },
+            Line::Length(length) => {
+                time_lines += 1;
+                let timing = Timing::Length(length);
+                if let Some((_, title)) = pending_title.take() {
+                    items.push(Item::Chapter(title, timing));
+                } else {
+                    if let Some((n, t, _)) = pending_timing.take() {
+                        diagnostics.push(missing_title(n, &t));
+                    }
+                    pending_timing = Some((number, text.to_string(), timing));
+                }
+            },
+            Line::Unparsed => {
+                diagnostics.push(TimestampDiagnostic { line: number, text: text.to_string(), kind: DiagnosticKind::Unparsed, message: "Line contains neither a title nor a time".to_string() });
+            },
+        }
+    }
+    if let Some((n, t)) = pending_title.take() {
+        diagnostics.push(missing_time(n, &t));
+    }
+    if let Some((n, t, _)) = pending_timing.take() {
+        diagnostics.push(missing_title(n, &t));
+    }
+    if title_lines != time_lines {
+        diagnostics.push(TimestampDiagnostic {
+            line: 0,
+            text: "".to_string(),
+            kind: DiagnosticKind::CountMismatch,
+            message: format!("Found {} title lines but {} time lines", title_lines, time_lines),
+        });
+    }
+    diagnostics.sort_by_key(|d| d.line);
+
+    let explicit_volumes = items.iter().any(|i| matches!(i, Item::Header(_)));
+    let (volume_titles, volumes) = if explicit_volumes { split_volumes_by_header(items) } else { split_volumes(items) };
+    ParsedTimestamps { volumes, volume_titles, explicit_volumes, diagnostics }
+}
+
+fn missing_time(line: usize, text: &str) -> TimestampDiagnostic {
+    TimestampDiagnostic { line, text: text.to_string(), kind: DiagnosticKind::MissingTime, message: "Title without a time".to_string() }
+}
+
+fn missing_title(line: usize, text: &str) -> TimestampDiagnostic {
+    TimestampDiagnostic { line, text: text.to_string(), kind: DiagnosticKind::MissingTitle, message: "Time without a title".to_string() }
+}
+
+fn parse_line(text: &str, patterns: &Patterns) -> Line {
+    if patterns.header.is_match(text) && !patterns.time.is_match(text) {
+        return Line::Header(clean_title(text.to_string()).trim().to_string());
+    }
+    // Ranges and times take precedence over lengths, as they are less ambiguous.
+    let (timing, rest) = if let Some(c) = patterns.range.captures(text) {
+        let m = c.get(0).unwrap();
+        let timing = Timing::Start(time_to_seconds(&c[1].to_string()), Some(time_to_seconds(&c[2].to_string())));
+        (Some(timing), remove_range(text, m.start(), m.end()))
+    } else if let Some(c) = patterns.time.captures(text) {
+        let m = c.get(0).unwrap();
+        (Some(Timing::Start(time_to_seconds(&c[1].to_string()), None)), remove_range(text, m.start(), m.end()))
+    } else if let Some(m) = patterns.length.find(text) {
+        (Some(Timing::Length(length_to_seconds(m.as_str(), patterns))), remove_range(text, m.start(), m.end()))
+    } else {
+        (None, text.to_string())
+    };
+
+    let title = to_title(&rest, patterns);
+    match (title, timing) {
+        (Some(title), Some(timing)) => Line::Entry(title, timing),
+        (Some(title), None) => Line::Title(title),
+        (None, Some(Timing::Start(start, end))) => Line::Time(start, end),
+        (None, Some(Timing::Length(length))) => Line::Length(length),
+        (None, None) => Line::Unparsed,
+    }
+}
+
+fn remove_range(text: &str, start: usize, end: usize) -> String {
+    format!("{} {}", &text[..start], &text[end..])
+}
+
+/// Cleans up what's left of a line after removing the time, e.g. numbering, brackets, separators.
+fn to_title(rest: &str, patterns: &Patterns) -> Option<String> {
+    // Numeric cells of tables are chapter numbers.
+    let rest = rest.split('\t')
+        .map(|c| c.trim())
+        .filter(|c| !c.trim_end_matches('.').chars().all(|ch| ch.is_ascii_digit()))
+        .collect::<Vec<&str>>()
+        .join(" ")
+        .replace("()", "")
+        .replace("[]", "");
+    let rest = patterns.numbering.replace(&rest, "");
+    let rest = rest.trim_matches(|c: char| c.is_whitespace() || "-–|:.,()[]•*".contains(c));
+    if !patterns.has_text.is_match(rest) {
+        return None;
+    }
+    Some(rest.to_string())
+}
+
+fn length_to_seconds(text: &str, patterns: &Patterns) -> usize {
+    patterns.length_part.captures_iter(text)
+        .map(|c| {
+            let value = c[1].parse::<usize>().unwrap_or_default();
+            match c[2].to_lowercase().as_str() {
+                "h" => value * 3600,
+                "m" => value * 60,
+                _ => value,
+            }
+        })
+        .sum()
+}
+
+/// Groups the chapters into volumes, starting a new volume every time the start time goes back.
+/// This is only a fallback for texts without volume headers.
+fn split_volumes(items: Vec<Item>) -> (Vec<String>, Vec<Vec<Scene>>) {
+    let mut result: Vec<Vec<Scene>> = vec![];
+    let mut last_start = usize::MAX;
+    // The start of the next scene, when using lengths.
+    let mut cursor = 0;
+
+    for item in items {
+        if let Item::Chapter(title, timing) = item {
+            let (start, end) = to_start_end(timing, cursor);
+            // New volume
+            if result.is_empty() || start < last_start {
+                result.push(vec![]);
+            }
+            last_start = start;
+            cursor = end.unwrap_or(start);
+            push_scene(result.last_mut().unwrap(), title, start, end);
+        }
+    }
+
+    result.iter_mut().for_each(|v| fill_ends(v));
+    let titles = (0..result.len()).map(|i| format!("Volume {}", i + 1)).collect();
+    (titles, result)
+}
+
+/// Groups the chapters into volumes, starting a new volume at every header.
+/// Chapters within a volume are sorted by start time.
+fn split_volumes_by_header(items: Vec<Item>) -> (Vec<String>, Vec<Vec<Scene>>) {
+    let mut titles: Vec<String> = vec![];
+    let mut result: Vec<Vec<Scene>> = vec![];
+    let mut cursor = 0;
+
+    for item in items {
+        match item {
+            Item::Header(header) => {
+                titles.push(header);
+                result.push(vec![]);
+                cursor = 0;
+            },
+            Item::Chapter(title, timing) => {
+                // Chapters before the first header.
+                if result.is_empty() {
+                    titles.push("Untitled".to_string());
+                    result.push(vec![]);
+                }
+                let (start, end) = to_start_end(timing, cursor);
+                cursor = end.unwrap_or(start);
+                push_scene(result.last_mut().unwrap(), title, start, end);
+            },
+        }
+    }
+
+    for volume in result.iter_mut() {
+        volume.sort_by_key(|s| s.start);
+        volume.iter_mut().enumerate().for_each(|(index, s)| s.index = index);
+        fill_ends(volume);
+    }
+    (titles, result)
+}
+
+fn to_start_end(timing: Timing, cursor: usize) -> (usize, Option<usize>) {
+    match timing {
+        Timing::Start(start, end) => (start, end),
+        Timing::Length(length) => (cursor, Some(cursor + length)),
+    }
+}
+
+fn push_scene(volume: &mut Vec<Scene>, title: String, start: usize, end: Option<usize>) {
+    let index = volume.len();
+    volume.push(Scene { index, title: clean_title(title), text: "".to_string(), start, end: end.unwrap_or(0), labels: vec![], file: "".to_string(), speed: 1.0 });
+}
+
+/// Scenes without an explicit end, end where the next scene starts.
+fn fill_ends(volume: &mut Vec<Scene>) {
+    for j in 0..volume.len() {
+        if volume[j].end == 0 && j + 1 < volume.len() {
+            volume[j].end = volume[j + 1].start;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn titles(volume: &Vec<Scene>) -> Vec<String> {
+        volume.iter().map(|s| s.title.to_string()).collect()
+    }
+
+    #[test]
+    fn test_fanatics_format() {
+        let parsed = parse_timestamps("Intro\n00:00:00 - 00:01:00\nArm Bar\n00:01:00 - 00:05:00\nTriangle\n00:00:00 - 00:10:00\n");
+        assert_eq!(2, parsed.volumes.len());
+        assert_eq!(vec!["Intro", "Arm Bar"], titles(&parsed.volumes[0]));
+        assert_eq!(60, parsed.volumes[0][1].start);
+        assert_eq!(300, parsed.volumes[0][1].end);
+        assert_eq!(0, parsed.volumes[1][0].index);
+        assert!(parsed.diagnostics.is_empty());
+    }
+
+    #[test]
+    fn test_inline_formats() {
+        let parsed = parse_timestamps("Intro 00:00\n01:30 Arm Bar\n3. Triangle (12:34)\n4\tKimura\t1:02:03\n");
+        assert_eq!(1, parsed.volumes.len());
+        assert_eq!(vec!["Intro", "Arm Bar", "Triangle", "Kimura"], titles(&parsed.volumes[0]));
+        assert_eq!(vec![0, 90, 754, 3723], parsed.volumes[0].iter().map(|s| s.start).collect::<Vec<usize>>());
+        assert_eq!(90, parsed.volumes[0][0].end);
+        assert!(parsed.diagnostics.is_empty());
+    }
+
+    #[test]
+    fn test_lengths() {
+        let parsed = parse_timestamps("Intro 1m\nArm Bar 5m 30s\nTriangle 1h 2m\n");
+        assert_eq!(vec![(0, 60), (60, 390), (390, 4110)], parsed.volumes[0].iter().map(|s| (s.start, s.end)).collect::<Vec<(usize, usize)>>());
+    }
+
+    #[test]
+    fn test_volume_headers() {
+        let parsed = parse_timestamps("Volume 1\nIntro\n00:00:00 - 00:01:00\nArm Bar\n00:01:00 - 00:05:00\nVolume 2: Attacks\nTriangle\n00:10:00 - 00:12:00\nKimura\n00:05:00 - 00:10:00\nDisc 3\nPart 1\n00:00:00 - 00:01:00\n");
+        assert!(parsed.explicit_volumes);
+        assert_eq!(vec!["Volume 1", "Volume 2 Attacks", "Disc 3"], parsed.volume_titles);
+        assert_eq!(3, parsed.volumes.len());
+        // Volume 2 starts at a non-zero time and isn't sorted.
+        assert_eq!(vec!["Kimura", "Triangle"], titles(&parsed.volumes[1]));
+        assert_eq!(vec![0, 1], parsed.volumes[1].iter().map(|s| s.index).collect::<Vec<usize>>());
+        // A header followed by a time is a chapter.
+        assert_eq!(vec!["Part 1"], titles(&parsed.volumes[2]));
+        assert!(parsed.diagnostics.is_empty());
+    }
+
+    #[test]
+    fn test_volumes_without_headers() {
+        let parsed = parse_timestamps("Intro 00:00\nArm Bar 01:00\nTriangle 00:00\n");
+        assert!(!parsed.explicit_volumes);
+        assert_eq!(vec!["Volume 1", "Volume 2"], parsed.volume_titles);
+    }
+
+    #[test]
+    fn test_stray_line_does_not_shift_titles() {
+        let parsed = parse_timestamps("Intro\n00:00:00 - 00:01:00\nBonus material\nArm Bar\n00:01:00 - 00:05:00\n---\nTriangle\n00:05:00 - 00:10:00\n");
+        assert_eq!(vec!["Intro", "Arm Bar", "Triangle"], titles(&parsed.volumes[0]));
+        assert_eq!(vec![(0, DiagnosticKind::CountMismatch), (3, DiagnosticKind::MissingTime), (6, DiagnosticKind::Unparsed)],
+                   parsed.diagnostics.iter().map(|d| (d.line, d.kind)).collect::<Vec<(usize, DiagnosticKind)>>());
+    }
+}
@@ -0,0 +1,466 @@
+use std::cell::RefCell;
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+use std::process::Stdio;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{channel, Sender, Receiver};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use crate::Player;
+use crate::PlayerMsg;
+use crate::PlayerEvent;
+use crate::PlayerState;
+use crate::MAIN_PLAYER;
+use crate::VLC_BACKEND;
+use crate::update_player_state;
+use crate::drill;
+use crate::requires_drill;
+use crate::PlaybackMode;
+use crate::PlaybackOptions;
+
+/// How often the state of VLC is polled.
+static VLC_POLL_INTERVAL_MILLIS: u64 = 500;
+/// How long to wait for VLC to start accepting connections.
+static VLC_STARTUP_ATTEMPTS: usize = 20;
+/// How often to check for a VLC that was launched outside the app.
+static VLC_ATTACH_INTERVAL_SECS: u64 = 2;
+/// How long to wait for VLC to release its address when stopping it.
+static VLC_STOP_TIMEOUT_MILLIS: u64 = 2000;
+/// How long to wait for VLC to respond to a command.
+static VLC_RESPONSE_TIMEOUT_MILLIS: u64 = 200;
+
+/// The address of the RC interface of the player. When no address is configured (or for players other than the main one),
+/// a free local port is used.
+pub fn vlc_rc_address(configured: &str, player: &str) -> String {
+    if !configured.trim().is_empty() && player == MAIN_PLAYER {
+        return configured.trim().to_string();
+    }
+    let port = TcpListener::bind("127.0.0.1:0")
+        .and_then(|l| l.local_addr())
+        .map(|a| a.port())
+        .unwrap_or(4212);
+    format!("127.0.0.1:{}", port)
+}
+
+/// A request to VLC, sent from the worker of the player so that the UI doesn't wait for VLC to respond.
+enum VlcRequest {
+    // Stops the previous instance and launches VLC with the arguments, then follows its state and drills the scene (start, end, options) if needed.
+    Open { file: String, args: Vec<String>, generation: Arc<AtomicUsize>, current: usize, sender: Sender<PlayerMsg>, drill: Option<(usize, usize, PlaybackOptions)> },
+    Stop,
+    Pause,
+    Seek(f64),
+    SetSpeed(f64),
+    FrameStep(bool),
+    Rewind(f64),
+}
+
+/// A VLC instance controlled over its RC (remote control) interface.
+pub struct VlcPlayer {
+    pub name: String,
+    pub address: String,
+    pub state: PlayerState,
+    // Incremented each time a file is opened, so that watchers of previous instances stop.
+    generation: Arc<AtomicUsize>,
+    last_attach_attempt: Option<Instant>,
+    sender: Sender<PlayerMsg>,
+    recv: Receiver<PlayerMsg>,
+    // Requests are handled one after the other, in the order they were made.
+    requests: Sender<VlcRequest>,
+}
+
+impl VlcPlayer {
+    pub fn new(name: &str, address: String) -> Self {
+        let (sender, recv) = channel();
+        let requests = vlc_worker(&address);
+        VlcPlayer { name: name.to_string(), address, state: PlayerState::new(), generation: Arc::new(AtomicUsize::new(0)), last_attach_attempt: None, sender, recv, requests }
+    }
+
+    fn request(&self, request: VlcRequest) {
+        let _ = self.requests.send(request);
+    }
+}
+
+impl Player for VlcPlayer {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn backend(&self) -> &'static str {
+        VLC_BACKEND
+    }
+
+    fn open(&mut self, file: &str, start: usize, end: usize, options: &PlaybackOptions) {
+        let current = self.generation.fetch_add(1, Ordering::SeqCst) + 1;
+        let options = options.for_range(start, end);
+        let mut args = vec!["--extraintf=rc".to_string(), format!("--rc-host={}", self.address)];
+        args.extend(vlc_range_args(start, end, &options));
+        args.push(file.to_string());
+        self.state = PlayerState::new();
+        self.state.running = true;
+        let drill = if requires_drill(&options) { Some((start, end, options)) } else { None };
+        self.request(VlcRequest::Open { file: file.to_string(), args, generation: self.generation.clone(), current, sender: self.sender.clone(), drill });
+    }
+
+    fn pause(&self) {
+        self.request(VlcRequest::Pause);
+    }
+
+    fn seek(&self, seconds: f64) {
+        self.request(VlcRequest::Seek(seconds));
+    }
+
+    fn stop(&mut self) {
+        self.generation.fetch_add(1, Ordering::SeqCst);
+        if self.state.running {
+            self.request(VlcRequest::Stop);
+        }
+        self.state = PlayerState::new();
+    }
+
+    fn close(&mut self) {
+        self.generation.fetch_add(1, Ordering::SeqCst);
+        vlc_stop(&self.address);
+        self.state = PlayerState::new();
+    }
+
+    fn set_speed(&self, speed: f64) {
+        self.request(VlcRequest::SetSpeed(speed));
+    }
+
+    fn frame_step(&self, forward: bool) {
+        self.request(VlcRequest::FrameStep(forward));
+    }
+
+    fn rewind(&self, seconds: f64) {
+        self.request(VlcRequest::Rewind(seconds));
+    }
+
+    fn attach(&mut self) {
+        if self.state.running || self.last_attach_attempt.map(|t| t.elapsed() < Duration::from_secs(VLC_ATTACH_INTERVAL_SECS)).unwrap_or(false) {
+            return;
+        }
+        self.last_attach_attempt = Some(Instant::now());
+        if vlc_command(&self.address, "status").is_some() {
+            self.state.running = true;
+            watch_vlc(&self.address, self.generation.clone(), self.generation.load(Ordering::SeqCst), self.sender.clone());
+        }
+    }
+
+    fn state(&self) -> &PlayerState {
+        &self.state
+    }
+
+    /// Actions can't be requested from inside VLC.
+    fn update(&mut self) -> Vec<PlayerEvent> {
+        update_player_state(&mut self.state, &mut self.recv);
+        vec![]
+    }
+}
+
+/// The VLC arguments that limit playback to the scene. Loops that need pauses or a count are driven by the app.
+pub fn vlc_range_args(start: usize, end: usize, options: &PlaybackOptions) -> Vec<String> {
+    let mut args = vec![format!("--start-time={}", start)];
+    if options.speed != 1.0 {
+        args.push(format!("--rate={}", options.speed));
+    }
+    match options.mode {
+        PlaybackMode::Continue => (),
+        PlaybackMode::SceneOnly => {
+            args.push(format!("--stop-time={}", end));
+            args.push("--play-and-pause".to_string());
+        },
+        PlaybackMode::Loop if requires_drill(options) => (),
+        PlaybackMode::Loop => {
+            args.push(format!("--stop-time={}", end));
+            args.push("--input-repeat=65535".to_string());
+        },
+    }
+    args
+}
+
+/// Sends the requests to VLC until the player is dropped.
+fn vlc_worker(address: &str) -> Sender<VlcRequest> {
+    let (requests, recv) = channel();
+    let address = address.to_string();
+    std::thread::spawn(move || {
+        while let Ok(request) = recv.recv() {
+            match request {
+                VlcRequest::Open { file, args, generation, current, sender, drill } => vlc_open(&address, &file, args, generation, current, sender, drill),
+                VlcRequest::Stop => vlc_stop(&address),
+                request => {
+                    if let Some(mut connection) = VlcConnection::connect(&address) {
+                        vlc_send(&mut connection, request);
+                    }
+                },
+            }
+        }
+    });
+    requests
+}
+
+fn vlc_send(connection: &mut VlcConnection, request: VlcRequest) {
+    match request {
+        VlcRequest::Pause => { connection.command("pause"); },
+        VlcRequest::Seek(seconds) => { connection.command(&format!("seek {}", seconds as usize)); },
+        VlcRequest::SetSpeed(speed) => { connection.command(&format!("rate {}", speed)); },
+        VlcRequest::FrameStep(true) => { connection.command("frame"); },
+        // The RC interface can only step forward.
+        VlcRequest::FrameStep(false) => {
+            vlc_set_pause(connection, true);
+            vlc_rewind(connection, 1.0);
+        },
+        VlcRequest::Rewind(seconds) => vlc_rewind(connection, seconds),
+        // Handled by the worker, as they don't need a connection.
+        VlcRequest::Open { .. } | VlcRequest::Stop => (),
+    }
+}
+
+/// Stops the previous instance (so that the address is free) and launches VLC for the file, unless another file was opened in the meantime.
+fn vlc_open(address: &str, file: &str, args: Vec<String>, generation: Arc<AtomicUsize>, current: usize, sender: Sender<PlayerMsg>, drill: Option<(usize, usize, PlaybackOptions)>) {
+    vlc_stop(address);
+    if generation.load(Ordering::SeqCst) != current {
+        return;
+    }
+    if !launch_vlc(file, args) {
+        let _ = sender.send(PlayerMsg::new());
+        return;
+    }
+    watch_vlc(address, generation.clone(), current, sender);
+    if let Some((start, end, options)) = drill {
+        let address = address.to_string();
+        std::thread::spawn(move || drill_vlc(&address, start, end, options, generation, current));
+    }
+}
+
+fn launch_vlc(file: &str, args: Vec<String>) -> bool {
+    let cmd = if cfg!(target_os = "windows") { "vlc.exe" } else { "vlc" };
+    let spawned = std::process::Command::new(cmd)
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .spawn();
+    if spawned.is_err() {
+        println!("Failed to start vlc for video: {}", file);
+        return false;
+    }
+    true
+}
+
+/// Shuts VLC down and waits for it to release its address, so that a new instance can listen on it.
+fn vlc_stop(address: &str) {
+    let mut connection = match VlcConnection::connect(address) {
+        Some(connection) => connection,
+        None => return,
+    };
+    println!("Stopping vlc.");
+    connection.command("shutdown");
+    drop(connection);
+    let started = Instant::now();
+    while started.elapsed() < Duration::from_millis(VLC_STOP_TIMEOUT_MILLIS) {
+        if VlcConnection::connect(address).is_none() {
+            println!("Stopped.");
+            return;
+        }
+        std::thread::sleep(Duration::from_millis(VLC_RESPONSE_TIMEOUT_MILLIS));
+    }
+    println!("Timed out waiting for vlc to stop.");
+}
+
+/// Drills the scene over a single connection, once VLC accepts connections.
+fn drill_vlc(address: &str, start: usize, end: usize, options: PlaybackOptions, generation: Arc<AtomicUsize>, current: usize) {
+    let mut attempts = 0;
+    let connection = loop {
+        if generation.load(Ordering::SeqCst) != current {
+            return;
+        }
+        match VlcConnection::connect(address) {
+            Some(connection) => break RefCell::new(connection),
+            None if attempts >= VLC_STARTUP_ATTEMPTS => {
+                println!("Failed to connect to vlc at: {}. The scene will not be drilled.", address);
+                return;
+            },
+            None => attempts += 1,
+        }
+        std::thread::sleep(Duration::from_millis(VLC_POLL_INTERVAL_MILLIS));
+    };
+    drill(start, end, options, generation, current,
+          || {
+              let time = connection.borrow_mut().command("get_time").and_then(|r| parse_rc_number(&r));
+              if time.is_none() {
+                  println!("Lost connection to vlc. Stopped drilling the scene.");
+              }
+              time
+          },
+          |pause| vlc_set_pause(&mut connection.borrow_mut(), pause),
+          |time| {
+              if connection.borrow_mut().command(&format!("seek {}", time as usize)).is_none() {
+                  println!("Failed to seek vlc to: {}", time);
+              }
+          });
+}
+
+fn vlc_rewind(connection: &mut VlcConnection, seconds: f64) {
+    if let Some(time) = connection.command("get_time").and_then(|r| parse_rc_number(&r)) {
+        connection.command(&format!("seek {}", (time - seconds).max(0.0) as usize));
+    }
+}
+
+/// The RC interface only toggles pause, so the current state is checked first.
+fn vlc_set_pause(connection: &mut VlcConnection, pause: bool) {
+    if !pause {
+        connection.command("play");
+        return;
+    }
+    let paused = connection.command("status").and_then(|r| parse_rc_status(&r).1).unwrap_or(false);
+    if !paused {
+        connection.command("pause");
+    }
+}
+
+/// Polls VLC for its state, until it exits or another file is opened (the generation is no longer `current`).
+fn watch_vlc(address: &str, generation: Arc<AtomicUsize>, current: usize, sender: Sender<PlayerMsg>) {
+    let address = address.to_string();
+    std::thread::spawn(move || {
+        let mut connected = false;
+        let mut attempts = 0;
+        while generation.load(Ordering::SeqCst) == current {
+            std::thread::sleep(Duration::from_millis(VLC_POLL_INTERVAL_MILLIS));
+            let state = get_vlc_state(&address);
+            // The state of a previous instance is no longer relevant.
+            if generation.load(Ordering::SeqCst) != current {
+                return;
+            }
+            match state {
+                Some(state) => {
+                    connected = true;
+                    let _ = sender.send(PlayerMsg::for_state(state));
+                },
+                None if connected || attempts >= VLC_STARTUP_ATTEMPTS => {
+                    let _ = sender.send(PlayerMsg::new());
+                    return;
+                },
+                None => attempts += 1,
+            }
+        }
+    });
+}
+
+fn get_vlc_state(address: &str) -> Option<PlayerState> {
+    let (path, pause) = parse_rc_status(&vlc_command(address, "status")?);
+    Some(PlayerState {
+        running: true,
+        pause: pause.unwrap_or(false),
+        path,
+        playback_time: vlc_command(address, "get_time").and_then(|r| parse_rc_number(&r)).unwrap_or(0.0),
+        duration: vlc_command(address, "get_length").and_then(|r| parse_rc_number(&r)).unwrap_or(0.0),
+    })
+}
+
+/// A connection to the RC interface, for sending several commands without reconnecting.
+pub struct VlcConnection {
+    stream: TcpStream,
+}
+
+impl VlcConnection {
+    pub fn connect(address: &str) -> Option<Self> {
+        let addr = address.to_socket_addrs().ok()?.next()?;
+        let stream = TcpStream::connect_timeout(&addr, Duration::from_millis(VLC_POLL_INTERVAL_MILLIS)).ok()?;
+        stream.set_read_timeout(Some(Duration::from_millis(VLC_RESPONSE_TIMEOUT_MILLIS))).ok()?;
+        Some(VlcConnection { stream })
+    }
+
+    /// Sends a command and returns the response (None if VLC is no longer reachable).
+    pub fn command(&mut self, command: &str) -> Option<String> {
+        self.stream.write_all(format!("{}\n", command).as_bytes()).ok()?;
+        // The interface doesn't delimit responses, so we read until it goes quiet.
+        let mut response = vec![];
+        let mut buffer = [0; 1024];
+        loop {
+            match self.stream.read(&mut buffer) {
+                // VLC closed the connection.
+                Ok(0) if response.is_empty() => return None,
+                Ok(0) => break,
+                Ok(n) => response.extend_from_slice(&buffer[..n]),
+                Err(_) => break,
+            }
+        }
+        Some(String::from_utf8_lossy(&response).to_string())
+    }
+}
+
+/// Sends a command to the RC interface over a new connection and returns the response (None if VLC is not reachable).
+pub fn vlc_command(address: &str, command: &str) -> Option<String> {
+    VlcConnection::connect(address)?.command(command)
+}
+
+/// The last number in the response, e.g. the output of `get_time`.
+pub fn parse_rc_number(response: &str) -> Option<f64> {
+    response.lines()
+        .map(|l| l.trim_start_matches('>').trim())
+        .filter_map(|l| l.parse::<f64>().ok())
+        .last()
+}
+
+/// The path of the current input and whether playback is paused, from the output of `status`.
+pub fn parse_rc_status(response: &str) -> (Option<String>, Option<bool>) {
+    let mut path = None;
+    let mut pause = None;
+    for line in response.lines().map(|l| l.trim_start_matches('>').trim()) {
+        if let Some(input) = line.strip_prefix("( new input:").and_then(|l| l.strip_suffix(')')) {
+            path = Some(decode_file_url(input.trim()));
+        }
+        if let Some(state) = line.strip_prefix("( state").and_then(|l| l.strip_suffix(')')) {
+            pause = Some(state.trim() == "paused");
+        }
+    }
+    (path, pause)
+}
+
+/// Converts a `file://` url to a path, decoding escaped characters (e.g. `%20`).
+fn decode_file_url(url: &str) -> String {
+    let url = url.strip_prefix("file://").unwrap_or(url);
+    let bytes = url.as_bytes();
+    let mut decoded: Vec<u8> = vec![];
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = if bytes[i] == b'%' && i + 2 < bytes.len() {
+            std::str::from_utf8(&bytes[i + 1..i + 3]).ok().and_then(|h| u8::from_str_radix(h, 16).ok())
+        } else {
+            None
+        };
+        match hex {
+            Some(b) => {
+                decoded.push(b);
+                i += 3;
+            },
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            },
+        }
+    }
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_rc_responses() {
+        let status = "VLC media player 3.0.16 Vetinari\nCommand Line Interface initialized. Type `help' for help.\n> ( new input: file:///videos/Back%20Attacks%20Vol%201.mp4 )\n( audio volume: 256 )\n( state paused )\n> ";
+        assert_eq!((Some("/videos/Back Attacks Vol 1.mp4".to_string()), Some(true)), parse_rc_status(status));
+        assert_eq!((None, None), parse_rc_status("> "));
+        assert_eq!(Some(125.0), parse_rc_number("Command Line Interface initialized. Type `help' for help.\n> 125\n> "));
+        assert_eq!(None, parse_rc_number("> "));
+    }
+
+    #[test]
+    fn test_vlc_range_args() {
+        let options = PlaybackOptions { mode: PlaybackMode::SceneOnly, loop_count: 0, loop_pause_secs: 0, speed: 1.0 };
+        assert_eq!(vec!["--start-time=60", "--stop-time=120", "--play-and-pause"], vlc_range_args(60, 120, &options));
+        let options = PlaybackOptions { mode: PlaybackMode::Loop, ..options };
+        assert_eq!(vec!["--start-time=60", "--stop-time=120", "--input-repeat=65535"], vlc_range_args(60, 120, &options));
+        assert_eq!(vec!["--start-time=60"], vlc_range_args(60, 120, &PlaybackOptions { loop_pause_secs: 2, ..options }));
+    }
+}